@@ -143,6 +143,58 @@ pub enum BumpErrorCode {
     ClaimUnqualified,
     #[msg("PoolMintSupplyIsZero")]
     PoolMintSupplyIsZero,
+    #[msg("OpenInterestCapTooSmall")]
+    OpenInterestCapTooSmall,
+    #[msg("MarketPaused")]
+    MarketPaused,
+    #[msg("MarketReduceOnly")]
+    MarketReduceOnly,
+    #[msg("UnStakePaused")]
+    UnStakePaused,
+    #[msg("NoMoreKeeperSpace")]
+    NoMoreKeeperSpace,
+    #[msg("CouldNotLoadKeeperRegistry")]
+    CouldNotLoadKeeperRegistry,
+    #[msg("DepositPaused")]
+    DepositPaused,
+    #[msg("WithdrawPaused")]
+    WithdrawPaused,
+    #[msg("PlaceOrderPaused")]
+    PlaceOrderPaused,
+    #[msg("ExecuteOrderPaused")]
+    ExecuteOrderPaused,
+    #[msg("LiquidationPaused")]
+    LiquidationPaused,
+    #[msg("StakeGloballyPaused")]
+    StakeGloballyPaused,
+    #[msg("UnStakeGloballyPaused")]
+    UnStakeGloballyPaused,
+    #[msg("OracleFeedIdMismatch")]
+    OracleFeedIdMismatch,
+    #[msg("OracleVerificationLevelTooLow")]
+    OracleVerificationLevelTooLow,
+    #[msg("OracleConfidenceTooWide")]
+    OracleConfidenceTooWide,
+    #[msg("OraclePriceDeviationTooLarge")]
+    OraclePriceDeviationTooLarge,
+    #[msg("MarketPriceDeviationTooLarge")]
+    MarketPriceDeviationTooLarge,
+    #[msg("CouldNotLoadOrderData")]
+    CouldNotLoadOrderData,
+    #[msg("OrderRentReceiverNotFound")]
+    OrderRentReceiverNotFound,
+    #[msg("OrderAccountsMissing")]
+    OrderAccountsMissing,
+    #[msg("OrderExpired")]
+    OrderExpired,
+    #[msg("OrderNotExpired")]
+    OrderNotExpired,
+    #[msg("OrderNotFullyFillable")]
+    OrderNotFullyFillable,
+    #[msg("TwapSliceNotDue")]
+    TwapSliceNotDue,
+    #[msg("HedgeModeChangeNotAllowed")]
+    HedgeModeChangeNotAllowed,
 }
 #[macro_export]
 macro_rules! print_error {
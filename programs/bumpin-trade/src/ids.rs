@@ -9,3 +9,23 @@ pub mod pyth_program {
     #[cfg(not(feature = "mainnet-beta"))]
     declare_id!("AQkVcL5spcyrqiKNJykGWGD78ry8Erkuub2t2ogUVWca");
 }
+
+pub mod pyth_receiver_program {
+    use anchor_lang::prelude::*;
+    declare_id!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+}
+
+pub mod pyth_push_oracle_program {
+    use anchor_lang::prelude::*;
+    declare_id!("pythWSnswVUd12oZpeFP8e9CVaEqJg25g1Vtc2biRsT");
+}
+
+pub mod switchboard_on_demand_program {
+    use anchor_lang::prelude::*;
+    #[cfg(feature = "mainnet-beta")]
+    declare_id!("SBondMDrcV3K4kxZR1HNVT7osZxAHVHgYXL5Ze1oMUv");
+    #[cfg(feature = "devnet")]
+    declare_id!("Aio4gaXjXzJNVLtzwtNVmSqGKpANtXhybbkhtAC94ji2");
+    #[cfg(not(any(feature = "mainnet-beta", feature = "devnet")))]
+    declare_id!("27ZntGb9wxMjmwN7vQ91Y4Gf7UYYB3GUG7XF3eAvJtSW");
+}
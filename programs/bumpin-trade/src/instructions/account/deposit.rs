@@ -3,6 +3,7 @@ use crate::is_normal;
 use crate::math::safe_math::SafeMath;
 use crate::processor::user_processor;
 use crate::state::bump_events::DepositEvent;
+use crate::state::state::{PauseOperation, State};
 use crate::state::trade_token::TradeToken;
 use crate::state::user::{User, UserTokenUpdateReason};
 use crate::utils::token;
@@ -12,6 +13,14 @@ use anchor_spl::token::{Token, TokenAccount};
 #[derive(Accounts)]
 #[instruction(_token_index: u16)]
 pub struct Deposit<'info> {
+    /// Required by the global deposit pause, an optional account would let callers skip it.
+    /// Clients resolving accounts from the idl derive it from its seeds.
+    #[account(
+        seeds = [b"bump_state".as_ref()],
+        bump,
+    )]
+    pub state: Box<Account<'info, State>>,
+
     #[account(
         mut,
         seeds = [b"user", authority.key().as_ref()],
@@ -46,6 +55,7 @@ pub struct Deposit<'info> {
 }
 
 pub fn handle_deposit(ctx: Context<Deposit>, _token_index: u16, amount: u128) -> Result<()> {
+    ctx.accounts.state.validate_operation_not_paused(PauseOperation::Deposit)?;
     let mut user = ctx.accounts.user.load_mut()?;
     let trade_token = &mut ctx.accounts.trade_token.load_mut()?;
     let token_mint = trade_token.mint_key;
@@ -7,10 +7,12 @@ use anchor_lang::solana_program::system_instruction::transfer;
 
 use crate::errors::BumpErrorCode;
 use crate::errors::BumpErrorCode::CantPayUserInitFee;
+use crate::instructions::constraints::*;
 use crate::state::bump_events::InitUserEvent;
 use crate::state::state::State;
 use crate::state::traits::Size;
 use crate::state::user::User;
+use crate::validate;
 
 #[derive(Accounts)]
 pub struct InitializeUser<'info> {
@@ -70,3 +72,25 @@ pub fn handle_initialize_user(ctx: Context<InitializeUser>) -> Result<()> {
     }
     Ok(())
 }
+
+#[derive(Accounts)]
+pub struct SetHedgeMode<'info> {
+    #[account(
+        mut,
+        seeds = [b"user", authority.key().as_ref()],
+        bump,
+        constraint = can_sign_for_user(& user, & authority) ? && is_normal(& user) ?,
+    )]
+    pub user: AccountLoader<'info, User>,
+
+    pub authority: Signer<'info>,
+}
+
+/// Positions are keyed by side only in hedge mode, so the mode can only change while the user
+/// holds no position.
+pub fn handle_set_hedge_mode(ctx: Context<SetHedgeMode>, hedge_mode: bool) -> Result<()> {
+    let mut user = ctx.accounts.user.load_mut()?;
+    validate!(!user.has_open_position(), BumpErrorCode::HedgeModeChangeNotAllowed)?;
+    user.hedge_mode = hedge_mode;
+    Ok(())
+}
@@ -4,7 +4,7 @@ use crate::is_normal;
 use crate::processor::optional_accounts::{load_maps, AccountMaps};
 use crate::processor::user_processor;
 use crate::state::bump_events::WithdrawEvent;
-use crate::state::state::State;
+use crate::state::state::{PauseOperation, State};
 use crate::state::user::User;
 use crate::{utils, validate};
 use anchor_lang::prelude::*;
@@ -57,6 +57,7 @@ pub fn handle_withdraw<'a, 'b, 'c: 'info, 'info>(
     token_index: u16,
     amount: u128,
 ) -> Result<()> {
+    ctx.accounts.state.validate_operation_not_paused(PauseOperation::Withdraw)?;
     validate!(amount > 0, BumpErrorCode::AmountZero)?;
 
     let mut user = ctx.accounts.user.load_mut()?;
@@ -4,7 +4,8 @@ use anchor_lang::prelude::*;
 
 use crate::math_error;
 use crate::safe_increment;
-use crate::state::market::{Market, MarketConfig};
+use crate::state::market::{CircuitBreakerAction, MarkPriceType, Market, MarketConfig};
+use crate::state::oracle::{validate_oracle, OracleSource};
 use crate::state::pool::Pool;
 use crate::state::state::State;
 use crate::traits::Size;
@@ -37,7 +38,7 @@ pub struct InitializeMarket<'info> {
     )]
     pub stable_pool: AccountLoader<'info, Pool>,
 
-    /// CHECK: checked by admin
+    /// CHECK: validated against index_oracle_source in handle_initialize_market
     pub index_mint_oracle: AccountInfo<'info>,
 
     #[account(mut)]
@@ -66,6 +67,11 @@ pub fn handle_initialize_market(
     ctx: Context<InitializeMarket>,
     params: InitializeMarketParams,
 ) -> Result<()> {
+    validate_oracle(
+        &ctx.accounts.index_mint_oracle,
+        params.index_oracle_source,
+        &params.index_oracle_feed_id,
+    )?;
     let mut market = ctx.accounts.market.load_init()?;
     let mut pool = ctx.accounts.pool.load_mut()?;
     let mut stable_pool = ctx.accounts.stable_pool.load_mut()?;
@@ -82,7 +88,10 @@ pub fn handle_initialize_market(
         maximum_leverage: params.maximum_leverage,
         minimum_leverage: params.minimum_leverage,
         max_pool_liquidity_share_rate: params.max_pool_liquidity_share_rate,
-        padding: [0; 4],
+        trigger_price_type: MarkPriceType::Spot,
+        liquidation_price_type: MarkPriceType::Spot,
+        pnl_price_type: MarkPriceType::Spot,
+        circuit_breaker_action: CircuitBreakerAction::Reject,
     };
 
     market.index = state.market_sequence;
@@ -90,6 +99,8 @@ pub fn handle_initialize_market(
     market.pool_key = pool.key;
     market.pool_mint_key = pool.mint_key;
     market.index_mint_oracle = ctx.accounts.index_mint_oracle.key();
+    market.index_oracle_source = params.index_oracle_source;
+    market.index_oracle_feed_id = params.index_oracle_feed_id;
     market.stable_pool_mint_key = stable_pool.mint_key;
     market.stable_pool_key = stable_pool.key;
     market.config = config;
@@ -114,4 +125,6 @@ pub struct InitializeMarketParams {
     pub pool_index: u16,
     pub stable_pool_index: u16,
     pub max_pool_liquidity_share_rate: u32,
+    pub index_oracle_source: OracleSource,
+    pub index_oracle_feed_id: [u8; 32],
 }
@@ -1,7 +1,11 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::Token;
 
+use crate::errors::BumpErrorCode;
+use crate::math::casting::Cast;
+use crate::math::constants::RATE_PRECISION;
 use crate::state::state::State;
+use crate::validate;
 
 #[derive(Accounts)]
 // #[instruction(param: InitializeStateParams)]
@@ -73,6 +77,11 @@ pub struct ModifyStateParams {
     pub staking_fee_reward_ratio: Option<u32>,
     pub pool_fee_reward_ratio: Option<u32>,
     pub essential_account_alt: Option<[u8; 32]>,
+    pub liquidator_reward_ratio: Option<u32>,
+    pub paused_operations: Option<u8>,
+    pub execution_fee: Option<u64>,
+    pub liquidation_buffer_rate: Option<u32>,
+    pub hedge_mm_offset_rate: Option<u32>,
 }
 
 #[track_caller]
@@ -98,10 +107,15 @@ pub fn handle_initialize_state(
         minimum_precision_multiple: initialize_state_params.min_precision_multiple,
         pool_rewards_interval_limit: initialize_state_params.pool_rewards_interval_limit,
         init_fee: initialize_state_params.init_fee,
+        execution_fee: 0,
         trading_fee_usd_pool_rewards_ratio: 0,
         pool_fee_reward_ratio: initialize_state_params.pool_fee_reward_ratio,
-        padding: [0u8; 5],
-        reserve_padding: [0u8; 32],
+        liquidator_reward_ratio: 0,
+        liquidation_buffer_rate: 0,
+        hedge_mm_offset_rate: 0,
+        paused_operations: 0,
+        padding: [0u8; 4],
+        pending_admin: Pubkey::default(),
     };
     Ok(())
 }
@@ -155,5 +169,32 @@ pub fn handle_modify_state(
     if let Some(essential_account_alt) = modify_state_params.essential_account_alt {
         state.essential_account_alt = Pubkey::new_from_array(essential_account_alt);
     }
+    if let Some(liquidator_reward_ratio) = modify_state_params.liquidator_reward_ratio {
+        validate!(
+            liquidator_reward_ratio.cast::<u128>()? <= RATE_PRECISION,
+            BumpErrorCode::InvalidParam
+        )?;
+        state.liquidator_reward_ratio = liquidator_reward_ratio;
+    }
+    if let Some(paused_operations) = modify_state_params.paused_operations {
+        state.paused_operations = paused_operations;
+    }
+    if let Some(execution_fee) = modify_state_params.execution_fee {
+        state.execution_fee = execution_fee;
+    }
+    if let Some(liquidation_buffer_rate) = modify_state_params.liquidation_buffer_rate {
+        validate!(
+            liquidation_buffer_rate.cast::<u128>()? <= RATE_PRECISION,
+            BumpErrorCode::InvalidParam
+        )?;
+        state.liquidation_buffer_rate = liquidation_buffer_rate;
+    }
+    if let Some(hedge_mm_offset_rate) = modify_state_params.hedge_mm_offset_rate {
+        validate!(
+            hedge_mm_offset_rate.cast::<u128>()? <= RATE_PRECISION,
+            BumpErrorCode::InvalidParam
+        )?;
+        state.hedge_mm_offset_rate = hedge_mm_offset_rate;
+    }
     Ok(())
 }
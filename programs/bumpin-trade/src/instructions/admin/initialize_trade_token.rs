@@ -5,6 +5,7 @@ use anchor_spl::token::{Mint, Token, TokenAccount};
 use crate::math::casting::Cast;
 use crate::math_error;
 use crate::safe_increment;
+use crate::state::oracle::{validate_oracle, OracleSource, OracleValidity, SecondaryOracle};
 use crate::state::state::State;
 use crate::state::trade_token::TradeToken;
 
@@ -28,7 +29,7 @@ pub struct InitializeTradeToken<'info> {
         token::authority = bump_signer
     )]
     pub trade_token_vault: Box<Account<'info, TokenAccount>>,
-    /// CHECK: ?
+    /// CHECK: validated against oracle_source in handle_initialize_trade_token
     pub oracle: AccountInfo<'info>,
     /// CHECK: ?
     #[account(
@@ -56,13 +57,20 @@ pub fn handle_initialize_trade_token(
     discount: u32,
     name: [u8; 32],
     liquidation_factor: u32,
+    oracle_source: OracleSource,
+    oracle_feed_id: [u8; 32],
 ) -> Result<()> {
+    validate_oracle(&ctx.accounts.oracle, oracle_source, &oracle_feed_id)?;
     let state = &mut ctx.accounts.state;
     let trade_token = &mut ctx.accounts.trade_token.load_init()?;
     **trade_token = TradeToken {
         mint_key: ctx.accounts.trade_token_vault.mint,
         name,
         oracle_key: *ctx.accounts.oracle.to_account_info().key,
+        oracle_feed_id,
+        oracle_validity: OracleValidity::default(),
+        secondary_oracle: SecondaryOracle::default(),
+        oracle_source,
         index: state.trade_token_sequence,
         discount,
         liquidation_factor,
@@ -70,8 +78,8 @@ pub fn handle_initialize_trade_token(
         total_liability: 0,
         total_amount: 0,
         vault_key: *ctx.accounts.trade_token_vault.to_account_info().key,
-        padding: [0; 4],
-        reserve_padding: [0; 32],
+        padding: [0; 3],
+        reserve_padding: [0; 16],
     };
     safe_increment!(state.trade_token_sequence, 1);
     Ok(())
@@ -0,0 +1,72 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::BumpErrorCode;
+use crate::state::keeper_registry::KeeperRegistry;
+use crate::state::state::State;
+use crate::traits::Size;
+use crate::validate;
+
+#[derive(Accounts)]
+pub struct InitializeKeeperRegistry<'info> {
+    #[account(
+        init,
+        seeds = [b"keeper_registry".as_ref()],
+        space = KeeperRegistry::SIZE,
+        bump,
+        payer = admin
+    )]
+    pub keeper_registry: AccountLoader<'info, KeeperRegistry>,
+
+    #[account(
+        seeds = [b"bump_state".as_ref()],
+        bump,
+        has_one = admin
+    )]
+    pub state: Account<'info, State>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub rent: Sysvar<'info, Rent>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetKeeper<'info> {
+    #[account(
+        mut,
+        seeds = [b"keeper_registry".as_ref()],
+        bump,
+    )]
+    pub keeper_registry: AccountLoader<'info, KeeperRegistry>,
+
+    #[account(
+        seeds = [b"bump_state".as_ref()],
+        bump,
+        has_one = admin
+    )]
+    pub state: Account<'info, State>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub struct SetKeeperParams {
+    pub keeper_key: [u8; 32],
+    pub roles: u8,
+}
+
+pub fn handle_initialize_keeper_registry(ctx: Context<InitializeKeeperRegistry>) -> Result<()> {
+    ctx.accounts.keeper_registry.load_init()?;
+    Ok(())
+}
+
+#[track_caller]
+pub fn handle_set_keeper(ctx: Context<SetKeeper>, params: SetKeeperParams) -> Result<()> {
+    let keeper_key = Pubkey::new_from_array(params.keeper_key);
+    validate!(!keeper_key.eq(&Pubkey::default()), BumpErrorCode::InvalidParam)?;
+    let mut keeper_registry = ctx.accounts.keeper_registry.load_mut()?;
+    keeper_registry.set_keeper(&keeper_key, params.roles)?;
+    Ok(())
+}
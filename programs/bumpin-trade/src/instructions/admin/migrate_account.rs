@@ -0,0 +1,154 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_lang::Discriminator;
+use arrayref::array_ref;
+
+use crate::errors::BumpErrorCode;
+use crate::state::market::Market;
+use crate::state::state::State;
+use crate::state::trade_token::TradeToken;
+use crate::traits::Size;
+use crate::validate;
+
+/// Accounts created before new fields were appended are shorter than the current layout, they are
+/// grown in place and the appended fields read as zero.
+#[derive(Accounts)]
+pub struct MigrateState<'info> {
+    #[account(
+        mut,
+        seeds = [b"bump_state".as_ref()],
+        bump,
+        owner = crate::id(),
+    )]
+    /// CHECK: can not be deserialized before it is grown, the admin is read from the raw data
+    pub state: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[track_caller]
+pub fn handle_migrate_state(ctx: Context<MigrateState>) -> Result<()> {
+    //admin is the first field of the state
+    let admin = {
+        let data = ctx.accounts.state.try_borrow_data()?;
+        validate!(data.len() >= 40, BumpErrorCode::InvalidParam)?;
+        Pubkey::try_from(&data[8..40]).map_err(|_e| BumpErrorCode::InvalidParam)?
+    };
+    validate!(admin.eq(ctx.accounts.admin.key), BumpErrorCode::InvalidParam)?;
+    realloc_account(
+        &ctx.accounts.state,
+        std::mem::size_of::<State>() + 8,
+        &ctx.accounts.admin,
+        &ctx.accounts.system_program,
+    )
+}
+
+#[derive(Accounts)]
+#[instruction(market_index: u16)]
+pub struct MigrateMarket<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", market_index.to_le_bytes().as_ref()],
+        bump,
+        owner = crate::id(),
+    )]
+    /// CHECK: can not be loaded before it is grown, the discriminator is checked in the handler
+    pub market: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"bump_state".as_ref()],
+        bump,
+        has_one = admin
+    )]
+    pub state: Account<'info, State>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(trade_token_index: u16)]
+pub struct MigrateTradeToken<'info> {
+    #[account(
+        mut,
+        seeds = [b"trade_token", trade_token_index.to_le_bytes().as_ref()],
+        bump,
+        owner = crate::id(),
+    )]
+    /// CHECK: can not be loaded before it is grown, the discriminator is checked in the handler
+    pub trade_token: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"bump_state".as_ref()],
+        bump,
+        has_one = admin
+    )]
+    pub state: Account<'info, State>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[track_caller]
+pub fn handle_migrate_market(ctx: Context<MigrateMarket>, _market_index: u16) -> Result<()> {
+    validate_discriminator(&ctx.accounts.market, &Market::discriminator())?;
+    realloc_account(
+        &ctx.accounts.market,
+        Market::SIZE,
+        &ctx.accounts.admin,
+        &ctx.accounts.system_program,
+    )
+}
+
+#[track_caller]
+pub fn handle_migrate_trade_token(
+    ctx: Context<MigrateTradeToken>,
+    _trade_token_index: u16,
+) -> Result<()> {
+    validate_discriminator(&ctx.accounts.trade_token, &TradeToken::discriminator())?;
+    realloc_account(
+        &ctx.accounts.trade_token,
+        TradeToken::SIZE,
+        &ctx.accounts.admin,
+        &ctx.accounts.system_program,
+    )
+}
+
+fn validate_discriminator(account: &AccountInfo, discriminator: &[u8; 8]) -> Result<()> {
+    let data = account.try_borrow_data()?;
+    validate!(
+        data.len() >= 8 && array_ref![data, 0, 8].eq(discriminator),
+        BumpErrorCode::InvalidParam
+    )?;
+    Ok(())
+}
+
+pub fn realloc_account<'info>(
+    account: &AccountInfo<'info>,
+    new_len: usize,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    if account.data_len() >= new_len {
+        return Ok(());
+    }
+    let rent_lamports = Rent::get()?.minimum_balance(new_len).saturating_sub(account.lamports());
+    if rent_lamports > 0 {
+        transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                Transfer { from: payer.to_account_info(), to: account.clone() },
+            ),
+            rent_lamports,
+        )?;
+    }
+    account.realloc(new_len, true)?;
+    Ok(())
+}
@@ -3,9 +3,25 @@ pub mod initialize_pool;
 pub mod initialize_rewards;
 pub mod initialize_state;
 pub mod initialize_trade_token;
+pub mod keeper_registry;
+pub mod migrate_account;
+pub mod modify_market;
+pub mod modify_pool;
+pub mod modify_trade_token;
+pub mod set_secondary_oracle;
+pub mod transfer_admin;
+pub mod update_keeper;
 
 pub use initialize_market::*;
 pub use initialize_pool::*;
 pub use initialize_rewards::*;
 pub use initialize_state::*;
 pub use initialize_trade_token::*;
+pub use keeper_registry::*;
+pub use migrate_account::*;
+pub use modify_market::*;
+pub use modify_pool::*;
+pub use modify_trade_token::*;
+pub use set_secondary_oracle::*;
+pub use transfer_admin::*;
+pub use update_keeper::*;
@@ -0,0 +1,202 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::BumpErrorCode;
+use crate::math::casting::Cast;
+use crate::math::constants::RATE_PRECISION;
+use crate::state::bump_events::MarketConfigUpdateEvent;
+use crate::state::market::{CircuitBreakerAction, MarkPriceType, Market, MarketStatus};
+use crate::state::oracle::{validate_oracle, OracleSource, OracleValidity};
+use crate::state::state::State;
+use crate::validate;
+
+#[derive(Accounts)]
+#[instruction(
+    params: ModifyMarketParams,
+)]
+pub struct ModifyMarket<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", params.market_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub market: AccountLoader<'info, Market>,
+
+    /// CHECK: validated by decoding a fresh price in handle_modify_market
+    pub index_mint_oracle: Option<AccountInfo<'info>>,
+
+    #[account(
+        seeds = [b"bump_state".as_ref()],
+        bump,
+        has_one = admin
+    )]
+    pub state: Account<'info, State>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ModifyMarketParams {
+    pub market_index: u16,
+    pub tick_size: Option<u128>,
+    pub open_fee_rate: Option<u128>,
+    pub close_fee_rate: Option<u128>,
+    pub maximum_long_open_interest_cap: Option<u128>,
+    pub maximum_short_open_interest_cap: Option<u128>,
+    pub long_short_ratio_limit: Option<u128>,
+    pub long_short_oi_bottom_limit: Option<u128>,
+    pub maximum_leverage: Option<u32>,
+    pub minimum_leverage: Option<u32>,
+    pub max_pool_liquidity_share_rate: Option<u32>,
+    pub market_status: Option<MarketStatus>,
+    pub index_oracle_source: Option<OracleSource>,
+    pub index_oracle_feed_id: Option<[u8; 32]>,
+    pub oracle_validity: Option<OracleValidity>,
+    pub ema_window_secs: Option<u32>,
+    pub trigger_price_type: Option<MarkPriceType>,
+    pub liquidation_price_type: Option<MarkPriceType>,
+    pub pnl_price_type: Option<MarkPriceType>,
+    pub max_price_deviation_bps: Option<u32>,
+    pub circuit_breaker_action: Option<CircuitBreakerAction>,
+    pub reset_mark_price: Option<bool>,
+    pub price_reanchor_count: Option<u16>,
+}
+
+#[track_caller]
+pub fn handle_modify_market(ctx: Context<ModifyMarket>, params: ModifyMarketParams) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    let pre_config = market.config;
+    let pre_market_status = market.market_status;
+    let pre_index_mint_oracle = market.index_mint_oracle;
+    let pre_index_oracle_source = market.index_oracle_source;
+    let pre_index_oracle_feed_id = market.index_oracle_feed_id;
+    let pre_oracle_validity = market.oracle_validity;
+
+    if let Some(tick_size) = params.tick_size {
+        market.config.tick_size = tick_size;
+    }
+    if let Some(open_fee_rate) = params.open_fee_rate {
+        market.config.open_fee_rate = open_fee_rate;
+    }
+    if let Some(close_fee_rate) = params.close_fee_rate {
+        market.config.close_fee_rate = close_fee_rate;
+    }
+    if let Some(maximum_long_open_interest_cap) = params.maximum_long_open_interest_cap {
+        market.config.maximum_long_open_interest_cap = maximum_long_open_interest_cap;
+    }
+    if let Some(maximum_short_open_interest_cap) = params.maximum_short_open_interest_cap {
+        market.config.maximum_short_open_interest_cap = maximum_short_open_interest_cap;
+    }
+    if let Some(long_short_ratio_limit) = params.long_short_ratio_limit {
+        market.config.long_short_ratio_limit = long_short_ratio_limit;
+    }
+    if let Some(long_short_oi_bottom_limit) = params.long_short_oi_bottom_limit {
+        market.config.long_short_oi_bottom_limit = long_short_oi_bottom_limit;
+    }
+    if let Some(maximum_leverage) = params.maximum_leverage {
+        market.config.maximum_leverage = maximum_leverage;
+    }
+    if let Some(minimum_leverage) = params.minimum_leverage {
+        market.config.minimum_leverage = minimum_leverage;
+    }
+    if let Some(max_pool_liquidity_share_rate) = params.max_pool_liquidity_share_rate {
+        market.config.max_pool_liquidity_share_rate = max_pool_liquidity_share_rate;
+    }
+    if let Some(market_status) = params.market_status {
+        market.market_status = market_status;
+        market.price_circuit_breaker_tripped = false;
+    }
+    if let Some(oracle_validity) = params.oracle_validity {
+        market.oracle_validity = oracle_validity;
+    }
+    if let Some(ema_window_secs) = params.ema_window_secs {
+        market.ema_window_secs = ema_window_secs;
+    }
+    if let Some(trigger_price_type) = params.trigger_price_type {
+        market.config.trigger_price_type = trigger_price_type;
+    }
+    if let Some(liquidation_price_type) = params.liquidation_price_type {
+        market.config.liquidation_price_type = liquidation_price_type;
+    }
+    if let Some(pnl_price_type) = params.pnl_price_type {
+        market.config.pnl_price_type = pnl_price_type;
+    }
+    if let Some(max_price_deviation_bps) = params.max_price_deviation_bps {
+        market.max_price_deviation_bps = max_price_deviation_bps;
+    }
+    if let Some(circuit_breaker_action) = params.circuit_breaker_action {
+        market.config.circuit_breaker_action = circuit_breaker_action;
+    }
+    //the next accepted index price becomes the new circuit breaker reference
+    if let Some(price_reanchor_count) = params.price_reanchor_count {
+        market.price_reanchor_count = price_reanchor_count;
+    }
+    if params.reset_mark_price.unwrap_or(false) {
+        market.mark_price.ema_price = 0u128;
+        market.deviated_price_count = 0;
+    }
+
+    match &ctx.accounts.index_mint_oracle {
+        Some(oracle) => {
+            //only accept an oracle that OracleMap will load and that currently yields a fresh price
+            let oracle_source = params.index_oracle_source.unwrap_or(market.index_oracle_source);
+            let oracle_feed_id = params.index_oracle_feed_id.unwrap_or(market.index_oracle_feed_id);
+            validate_oracle(oracle, oracle_source, &oracle_feed_id)?;
+            market.index_mint_oracle = oracle.key();
+            market.index_oracle_source = oracle_source;
+            market.index_oracle_feed_id = oracle_feed_id;
+        },
+        None => validate!(
+            params.index_oracle_source.is_none() && params.index_oracle_feed_id.is_none(),
+            BumpErrorCode::InvalidParam
+        )?,
+    }
+
+    validate_market_config(&market)?;
+
+    emit!(MarketConfigUpdateEvent {
+        market_key: ctx.accounts.market.key(),
+        symbol: market.symbol,
+        pre_config,
+        config: market.config,
+        pre_market_status,
+        market_status: market.market_status,
+        pre_index_mint_oracle,
+        index_mint_oracle: market.index_mint_oracle,
+        pre_index_oracle_source,
+        index_oracle_source: market.index_oracle_source,
+        pre_index_oracle_feed_id,
+        index_oracle_feed_id: market.index_oracle_feed_id,
+        pre_oracle_validity,
+        oracle_validity: market.oracle_validity,
+    });
+    Ok(())
+}
+
+fn validate_market_config(market: &Market) -> Result<()> {
+    let config = &market.config;
+    validate!(config.tick_size > 0u128, BumpErrorCode::InvalidParam)?;
+    validate!(
+        config.open_fee_rate < RATE_PRECISION && config.close_fee_rate < RATE_PRECISION,
+        BumpErrorCode::InvalidParam
+    )?;
+    validate!(
+        config.minimum_leverage > 0u32 && config.minimum_leverage <= config.maximum_leverage,
+        BumpErrorCode::InvalidParam
+    )?;
+    validate!(
+        config.max_pool_liquidity_share_rate.cast::<u128>()? <= RATE_PRECISION,
+        BumpErrorCode::InvalidParam
+    )?;
+    market.oracle_validity.validate_config()?;
+
+    //caps can not be lowered below the open interest already held by open positions
+    validate!(
+        config.maximum_long_open_interest_cap >= market.long_open_interest.open_interest,
+        BumpErrorCode::OpenInterestCapTooSmall
+    )?;
+    validate!(
+        config.maximum_short_open_interest_cap >= market.short_open_interest.open_interest,
+        BumpErrorCode::OpenInterestCapTooSmall
+    )?;
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::BumpErrorCode;
+use crate::math::casting::Cast;
+use crate::math::constants::RATE_PRECISION;
+use crate::state::bump_events::PoolConfigUpdateEvent;
+use crate::state::pool::{Pool, PoolStatus};
+use crate::state::state::State;
+use crate::validate;
+
+#[derive(Accounts)]
+#[instruction(
+    params: ModifyPoolParams,
+)]
+pub struct ModifyPool<'info> {
+    #[account(
+        mut,
+        seeds = [b"pool".as_ref(), params.pool_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        seeds = [b"bump_state".as_ref()],
+        bump,
+        has_one = admin
+    )]
+    pub state: Account<'info, State>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ModifyPoolParams {
+    pub pool_index: u16,
+    pub minimum_stake_amount: Option<u128>,
+    pub minimum_un_stake_amount: Option<u128>,
+    pub pool_liquidity_limit: Option<u128>,
+    pub borrowing_interest_rate: Option<u128>,
+    pub stake_fee_rate: Option<u32>,
+    pub un_stake_fee_rate: Option<u32>,
+    pub un_settle_mint_ratio_limit: Option<u32>,
+    pub status: Option<PoolStatus>,
+}
+
+#[track_caller]
+pub fn handle_modify_pool(ctx: Context<ModifyPool>, params: ModifyPoolParams) -> Result<()> {
+    let mut pool = ctx.accounts.pool.load_mut()?;
+    let pre_config = pool.config;
+    let pre_status = pool.status;
+
+    if let Some(borrowing_interest_rate) = params.borrowing_interest_rate {
+        //settle the borrowing fee accrued so far with the old rate
+        pool.update_pool_borrowing_fee_rate()?;
+        pool.config.borrowing_interest_rate = borrowing_interest_rate;
+    }
+    if let Some(minimum_stake_amount) = params.minimum_stake_amount {
+        pool.config.minimum_stake_amount = minimum_stake_amount;
+    }
+    if let Some(minimum_un_stake_amount) = params.minimum_un_stake_amount {
+        pool.config.minimum_un_stake_amount = minimum_un_stake_amount;
+    }
+    if let Some(pool_liquidity_limit) = params.pool_liquidity_limit {
+        pool.config.pool_liquidity_limit = pool_liquidity_limit;
+    }
+    if let Some(stake_fee_rate) = params.stake_fee_rate {
+        pool.config.stake_fee_rate = stake_fee_rate;
+    }
+    if let Some(un_stake_fee_rate) = params.un_stake_fee_rate {
+        pool.config.un_stake_fee_rate = un_stake_fee_rate;
+    }
+    if let Some(un_settle_mint_ratio_limit) = params.un_settle_mint_ratio_limit {
+        pool.config.un_settle_mint_ratio_limit = un_settle_mint_ratio_limit;
+    }
+    if let Some(status) = params.status {
+        pool.status = status;
+    }
+
+    validate!(
+        pool.config.stake_fee_rate.cast::<u128>()? < RATE_PRECISION
+            && pool.config.un_stake_fee_rate.cast::<u128>()? < RATE_PRECISION,
+        BumpErrorCode::InvalidParam
+    )?;
+    validate!(
+        pool.config.un_settle_mint_ratio_limit.cast::<u128>()? <= RATE_PRECISION,
+        BumpErrorCode::InvalidParam
+    )?;
+
+    emit!(PoolConfigUpdateEvent {
+        pool_key: pool.key,
+        pool_mint: pool.mint_key,
+        pre_config,
+        config: pool.config,
+        pre_status,
+        status: pool.status,
+    });
+    Ok(())
+}
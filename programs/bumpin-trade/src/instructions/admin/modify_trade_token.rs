@@ -0,0 +1,108 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::BumpErrorCode;
+use crate::math::casting::Cast;
+use crate::math::constants::RATE_PRECISION;
+use crate::state::bump_events::TradeTokenConfigUpdateEvent;
+use crate::state::oracle::{validate_oracle, OracleSource, OracleValidity};
+use crate::state::state::State;
+use crate::state::trade_token::TradeToken;
+use crate::validate;
+
+#[derive(Accounts)]
+#[instruction(
+    params: ModifyTradeTokenParams,
+)]
+pub struct ModifyTradeToken<'info> {
+    #[account(
+        mut,
+        seeds = [b"trade_token".as_ref(), params.trade_token_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub trade_token: AccountLoader<'info, TradeToken>,
+
+    /// CHECK: validated by decoding a fresh price in handle_modify_trade_token
+    pub oracle: Option<AccountInfo<'info>>,
+
+    #[account(
+        seeds = [b"bump_state".as_ref()],
+        bump,
+        has_one = admin
+    )]
+    pub state: Account<'info, State>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ModifyTradeTokenParams {
+    pub trade_token_index: u16,
+    pub discount: Option<u32>,
+    pub liquidation_factor: Option<u32>,
+    pub oracle_source: Option<OracleSource>,
+    pub oracle_feed_id: Option<[u8; 32]>,
+    pub oracle_validity: Option<OracleValidity>,
+}
+
+#[track_caller]
+pub fn handle_modify_trade_token(
+    ctx: Context<ModifyTradeToken>,
+    params: ModifyTradeTokenParams,
+) -> Result<()> {
+    let mut trade_token = ctx.accounts.trade_token.load_mut()?;
+    let pre_discount = trade_token.discount;
+    let pre_liquidation_factor = trade_token.liquidation_factor;
+    let pre_oracle_key = trade_token.oracle_key;
+    let pre_oracle_source = trade_token.oracle_source;
+    let pre_oracle_feed_id = trade_token.oracle_feed_id;
+    let pre_oracle_validity = trade_token.oracle_validity;
+
+    if let Some(discount) = params.discount {
+        validate!(discount.cast::<u128>()? <= RATE_PRECISION, BumpErrorCode::InvalidParam)?;
+        trade_token.discount = discount;
+    }
+    if let Some(liquidation_factor) = params.liquidation_factor {
+        validate!(
+            liquidation_factor.cast::<u128>()? <= RATE_PRECISION,
+            BumpErrorCode::InvalidParam
+        )?;
+        trade_token.liquidation_factor = liquidation_factor;
+    }
+    if let Some(oracle_validity) = params.oracle_validity {
+        oracle_validity.validate_config()?;
+        trade_token.oracle_validity = oracle_validity;
+    }
+    match &ctx.accounts.oracle {
+        Some(oracle) => {
+            //only accept an oracle that OracleMap will load and that currently yields a fresh price
+            let oracle_source = params.oracle_source.unwrap_or(trade_token.oracle_source);
+            let oracle_feed_id = params.oracle_feed_id.unwrap_or(trade_token.oracle_feed_id);
+            validate_oracle(oracle, oracle_source, &oracle_feed_id)?;
+            trade_token.oracle_key = oracle.key();
+            trade_token.oracle_source = oracle_source;
+            trade_token.oracle_feed_id = oracle_feed_id;
+        },
+        None => validate!(
+            params.oracle_source.is_none() && params.oracle_feed_id.is_none(),
+            BumpErrorCode::InvalidParam
+        )?,
+    }
+
+    emit!(TradeTokenConfigUpdateEvent {
+        trade_token_key: ctx.accounts.trade_token.key(),
+        mint_key: trade_token.mint_key,
+        pre_discount,
+        discount: trade_token.discount,
+        pre_liquidation_factor,
+        liquidation_factor: trade_token.liquidation_factor,
+        pre_oracle_key,
+        oracle_key: trade_token.oracle_key,
+        pre_oracle_source,
+        oracle_source: trade_token.oracle_source,
+        pre_oracle_feed_id,
+        oracle_feed_id: trade_token.oracle_feed_id,
+        pre_oracle_validity,
+        oracle_validity: trade_token.oracle_validity,
+    });
+    Ok(())
+}
@@ -0,0 +1,159 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::BumpErrorCode;
+use crate::math::casting::Cast;
+use crate::math::constants::BPS_PRECISION;
+use crate::state::bump_events::SecondaryOracleUpdateEvent;
+use crate::state::market::Market;
+use crate::state::oracle::{
+    validate_oracle, OracleAggregation, OracleFeed, OracleSource, SecondaryOracle,
+    MAX_SECONDARY_ORACLES,
+};
+use crate::state::state::State;
+use crate::state::trade_token::TradeToken;
+use crate::validate;
+
+#[derive(Accounts)]
+#[instruction(
+    params: SetSecondaryOracleParams,
+)]
+pub struct SetTradeTokenSecondaryOracle<'info> {
+    #[account(
+        mut,
+        seeds = [b"trade_token".as_ref(), params.index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub trade_token: AccountLoader<'info, TradeToken>,
+
+    #[account(
+        seeds = [b"bump_state".as_ref()],
+        bump,
+        has_one = admin
+    )]
+    pub state: Account<'info, State>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    params: SetSecondaryOracleParams,
+)]
+pub struct SetMarketSecondaryOracle<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", params.index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub market: AccountLoader<'info, Market>,
+
+    #[account(
+        seeds = [b"bump_state".as_ref()],
+        bump,
+        has_one = admin
+    )]
+    pub state: Account<'info, State>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub struct OracleFeedParams {
+    pub oracle_source: OracleSource,
+    pub feed_id: [u8; 32],
+}
+
+/// The secondary oracle accounts are passed in the remaining accounts, in the order of `feeds`.
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Eq, PartialEq)]
+pub struct SetSecondaryOracleParams {
+    pub index: u16,
+    pub feeds: Vec<OracleFeedParams>,
+    pub aggregation: OracleAggregation,
+    pub max_deviation_bps: u32,
+}
+
+#[track_caller]
+pub fn handle_set_trade_token_secondary_oracle(
+    ctx: Context<SetTradeTokenSecondaryOracle>,
+    params: SetSecondaryOracleParams,
+) -> Result<()> {
+    let mut trade_token = ctx.accounts.trade_token.load_mut()?;
+    let pre_secondary_oracle = trade_token.secondary_oracle;
+    trade_token.secondary_oracle =
+        build_secondary_oracle(&trade_token.oracle_key, ctx.remaining_accounts, &params)?;
+
+    emit!(SecondaryOracleUpdateEvent {
+        key: ctx.accounts.trade_token.key(),
+        oracle_key: trade_token.oracle_key,
+        pre_secondary_oracle,
+        secondary_oracle: trade_token.secondary_oracle,
+    });
+    Ok(())
+}
+
+#[track_caller]
+pub fn handle_set_market_secondary_oracle(
+    ctx: Context<SetMarketSecondaryOracle>,
+    params: SetSecondaryOracleParams,
+) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    let pre_secondary_oracle = market.index_secondary_oracle;
+    market.index_secondary_oracle =
+        build_secondary_oracle(&market.index_mint_oracle, ctx.remaining_accounts, &params)?;
+
+    emit!(SecondaryOracleUpdateEvent {
+        key: ctx.accounts.market.key(),
+        oracle_key: market.index_mint_oracle,
+        pre_secondary_oracle,
+        secondary_oracle: market.index_secondary_oracle,
+    });
+    Ok(())
+}
+
+fn build_secondary_oracle(
+    primary_oracle: &Pubkey,
+    oracle_accounts: &[AccountInfo],
+    params: &SetSecondaryOracleParams,
+) -> Result<SecondaryOracle> {
+    //OracleAggregation::None clears the secondary oracles
+    if params.aggregation == OracleAggregation::None {
+        return Ok(SecondaryOracle::default());
+    }
+    validate!(
+        !params.feeds.is_empty()
+            && params.feeds.len() <= MAX_SECONDARY_ORACLES
+            && oracle_accounts.len() >= params.feeds.len(),
+        BumpErrorCode::InvalidParam
+    )?;
+    validate!(
+        params.max_deviation_bps.cast::<u128>()? <= BPS_PRECISION,
+        BumpErrorCode::InvalidParam
+    )?;
+    validate!(
+        params.aggregation != OracleAggregation::MaxDeviation || params.max_deviation_bps > 0,
+        BumpErrorCode::InvalidParam
+    )?;
+
+    let mut secondary_oracle = SecondaryOracle {
+        max_deviation_bps: params.max_deviation_bps,
+        aggregation: params.aggregation,
+        ..Default::default()
+    };
+    for (index, feed) in params.feeds.iter().enumerate() {
+        let oracle_account = &oracle_accounts[index];
+        //every source has to be distinct for the median to mean anything
+        validate!(
+            !oracle_account.key.eq(primary_oracle)
+                && !oracle_accounts[..index].iter().any(|other| other.key.eq(oracle_account.key)),
+            BumpErrorCode::InvalidParam
+        )?;
+        validate_oracle(oracle_account, feed.oracle_source, &feed.feed_id)?;
+        secondary_oracle.oracles[index] = OracleFeed {
+            oracle_key: oracle_account.key(),
+            feed_id: feed.feed_id,
+            oracle_source: feed.oracle_source,
+            padding: [0; 7],
+        };
+    }
+    Ok(secondary_oracle)
+}
@@ -0,0 +1,47 @@
+use anchor_lang::prelude::*;
+
+use crate::state::bump_events::AdminUpdateEvent;
+use crate::state::state::State;
+
+#[derive(Accounts)]
+pub struct TransferAdmin<'info> {
+    #[account(
+        mut,
+        seeds = [b"bump_state".as_ref()],
+        bump,
+        has_one = admin
+    )]
+    pub state: Account<'info, State>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(
+        mut,
+        seeds = [b"bump_state".as_ref()],
+        bump,
+        has_one = pending_admin
+    )]
+    pub state: Account<'info, State>,
+
+    pub pending_admin: Signer<'info>,
+}
+
+#[track_caller]
+pub fn handle_transfer_admin(ctx: Context<TransferAdmin>, new_admin: [u8; 32]) -> Result<()> {
+    //the new admin only takes over after signing accept_admin, proposing the default key cancels
+    ctx.accounts.state.pending_admin = Pubkey::new_from_array(new_admin);
+    Ok(())
+}
+
+#[track_caller]
+pub fn handle_accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let pre_admin = state.admin;
+    state.admin = state.pending_admin;
+    state.pending_admin = Pubkey::default();
+    emit!(AdminUpdateEvent { pre_admin, admin: state.admin });
+    Ok(())
+}
@@ -0,0 +1,26 @@
+use anchor_lang::prelude::*;
+
+use crate::state::bump_events::KeeperUpdateEvent;
+use crate::state::state::State;
+
+#[derive(Accounts)]
+pub struct UpdateKeeper<'info> {
+    #[account(
+        mut,
+        seeds = [b"bump_state".as_ref()],
+        bump,
+        has_one = admin
+    )]
+    pub state: Account<'info, State>,
+
+    pub admin: Signer<'info>,
+}
+
+#[track_caller]
+pub fn handle_update_keeper(ctx: Context<UpdateKeeper>, keeper_key: [u8; 32]) -> Result<()> {
+    let state = &mut ctx.accounts.state;
+    let pre_keeper_key = state.keeper_key;
+    state.keeper_key = Pubkey::new_from_array(keeper_key);
+    emit!(KeeperUpdateEvent { pre_keeper_key, keeper_key: state.keeper_key });
+    Ok(())
+}
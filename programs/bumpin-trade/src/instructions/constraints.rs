@@ -1,6 +1,9 @@
 use anchor_lang::accounts::account_loader::AccountLoader;
 use anchor_lang::accounts::signer::Signer;
+use anchor_lang::prelude::AccountInfo;
 
+use crate::state::keeper_registry::{KeeperRegistry, KeeperRole};
+use crate::state::state::State;
 use crate::state::user::{User, UserStatus};
 
 pub fn can_sign_for_user(user: &AccountLoader<User>, signer: &Signer) -> anchor_lang::Result<bool> {
@@ -10,3 +13,17 @@ pub fn can_sign_for_user(user: &AccountLoader<User>, signer: &Signer) -> anchor_
 pub fn is_normal(user: &AccountLoader<User>) -> anchor_lang::Result<bool> {
     user.load().map(|user| user.user_status.eq(&UserStatus::NORMAL))
 }
+
+/// State.keeper_key keeps every role, rotate it to the default key to rely on the registry only.
+/// Until initialize_keeper_registry is called only State.keeper_key can sign.
+pub fn can_sign_for_keeper(
+    state: &State,
+    keeper_registry: &AccountInfo,
+    signer: &Signer,
+    role: KeeperRole,
+) -> anchor_lang::Result<bool> {
+    if state.keeper_key.eq(signer.key) {
+        return Ok(true);
+    }
+    Ok(KeeperRegistry::has_role_in_account(keeper_registry, signer.key, role)?)
+}
@@ -1,3 +1,5 @@
+use crate::instructions::constraints::can_sign_for_keeper;
+use crate::state::keeper_registry::KeeperRole;
 use std::ops::DerefMut;
 
 use anchor_lang::prelude::*;
@@ -10,8 +12,9 @@ use crate::processor::optional_accounts::{load_maps, AccountMaps};
 use crate::processor::position_processor;
 use crate::processor::position_processor::DecreasePositionParams;
 use crate::state::market::Market;
+use crate::state::order_map::OrderMap;
 use crate::state::pool::Pool;
-use crate::state::state::State;
+use crate::state::state::{PauseOperation, State};
 use crate::state::trade_token::TradeToken;
 use crate::state::User;
 use crate::validate;
@@ -23,7 +26,6 @@ use crate::validate;
 pub struct ADLIsolate<'info> {
     #[account(
         seeds = [b"bump_state".as_ref()],
-        has_one = keeper_key,
         bump,
     )]
     pub state: Box<Account<'info, State>>,
@@ -99,7 +101,14 @@ pub struct ADLIsolate<'info> {
     pub trade_token_vault: Box<Account<'info, TokenAccount>>,
 
     #[account(
-        constraint = state.keeper_key.eq(& keeper_key.key())
+        seeds = [b"keeper_registry".as_ref()],
+        bump,
+    )]
+    /// CHECK: optional keeper registry, checked by can_sign_for_keeper
+    pub keeper_registry: AccountInfo<'info>,
+
+    #[account(
+        constraint = can_sign_for_keeper(& state, & keeper_registry, & keeper_key, KeeperRole::ADL) ?
     )]
     pub keeper_key: Signer<'info>,
 
@@ -117,6 +126,8 @@ pub fn handle_adl_isolate<'a, 'b, 'c: 'info, 'info>(
     ctx: Context<'a, 'b, 'c, 'info, ADLIsolate<'info>>,
     params: ADLParams,
 ) -> Result<()> {
+    //adl is a forced close, it stops with liquidations
+    ctx.accounts.state.validate_operation_not_paused(PauseOperation::Liquidation)?;
     let mut pool = ctx.accounts.pool.load_mut()?;
     let mut stable_pool = ctx.accounts.stable_pool.load_mut()?;
 
@@ -133,6 +144,9 @@ pub fn handle_adl_isolate<'a, 'b, 'c: 'info, 'info>(
 
     let remaining_accounts = ctx.remaining_accounts;
     let AccountMaps { mut oracle_map, .. } = load_maps(remaining_accounts)?;
+    let mut order_map = OrderMap::load(remaining_accounts, &user_account)?;
+    oracle_map.register_market(&*market_account_loader.load()?);
+    oracle_map.register_trade_token(&*trade_token_loader.load()?);
 
     let position = user_account.get_user_position_ref(&params.position_key)?;
     validate!(!position.is_portfolio_margin, OnlyIsolatePositionAllowed)?;
@@ -165,6 +179,7 @@ pub fn handle_adl_isolate<'a, 'b, 'c: 'info, 'info>(
         bump_signer_account_info,
         token_program,
         &mut oracle_map,
+        &mut order_map,
         &position_key,
     )?;
     Ok(())
@@ -177,7 +192,6 @@ pub fn handle_adl_isolate<'a, 'b, 'c: 'info, 'info>(
 pub struct ADLCross<'info> {
     #[account(
         seeds = [b"bump_state".as_ref()],
-        has_one = keeper_key,
         bump,
     )]
     pub state: Box<Account<'info, State>>,
@@ -246,7 +260,14 @@ pub struct ADLCross<'info> {
     pub trade_token_vault: Box<Account<'info, TokenAccount>>,
 
     #[account(
-        constraint = state.keeper_key.eq(& keeper_key.key())
+        seeds = [b"keeper_registry".as_ref()],
+        bump,
+    )]
+    /// CHECK: optional keeper registry, checked by can_sign_for_keeper
+    pub keeper_registry: AccountInfo<'info>,
+
+    #[account(
+        constraint = can_sign_for_keeper(& state, & keeper_registry, & keeper_key, KeeperRole::ADL) ?
     )]
     pub keeper_key: Signer<'info>,
 
@@ -264,6 +285,8 @@ pub fn handle_adl_cross<'a, 'b, 'c: 'info, 'info>(
     ctx: Context<'a, 'b, 'c, 'info, ADLCross<'info>>,
     params: ADLParams,
 ) -> Result<()> {
+    //adl is a forced close, it stops with liquidations
+    ctx.accounts.state.validate_operation_not_paused(PauseOperation::Liquidation)?;
     let mut pool = ctx.accounts.pool.load_mut()?;
     let mut stable_pool = ctx.accounts.stable_pool.load_mut()?;
 
@@ -279,6 +302,9 @@ pub fn handle_adl_cross<'a, 'b, 'c: 'info, 'info>(
 
     let remaining_accounts = ctx.remaining_accounts;
     let AccountMaps { mut oracle_map, .. } = load_maps(remaining_accounts)?;
+    let mut order_map = OrderMap::load(remaining_accounts, &user_account)?;
+    oracle_map.register_market(&*market_account_loader.load()?);
+    oracle_map.register_trade_token(&*trade_token_loader.load()?);
 
     let position = user_account.get_user_position_ref(&params.position_key)?;
     validate!(position.is_portfolio_margin, OnlyCrossPositionAllowed)?;
@@ -311,6 +337,7 @@ pub fn handle_adl_cross<'a, 'b, 'c: 'info, 'info>(
         bump_signer_account_info,
         token_program,
         &mut oracle_map,
+        &mut order_map,
         &position_key,
     )?;
     Ok(())
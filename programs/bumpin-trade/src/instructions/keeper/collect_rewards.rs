@@ -1,3 +1,5 @@
+use crate::instructions::constraints::can_sign_for_keeper;
+use crate::state::keeper_registry::KeeperRole;
 use anchor_lang::prelude::*;
 use anchor_spl::token::{Token, TokenAccount};
 
@@ -20,7 +22,6 @@ use crate::validate;
 pub struct CollectRewards<'info> {
     #[account(
         seeds = [b"bump_state".as_ref()],
-        has_one = keeper_key,
         has_one = bump_signer,
         bump,
     )]
@@ -83,7 +84,14 @@ pub struct CollectRewards<'info> {
     pub dao_rewards_vault: Box<Account<'info, TokenAccount>>,
 
     #[account(
-        constraint = state.keeper_key.eq(& keeper_key.key())
+        seeds = [b"keeper_registry".as_ref()],
+        bump,
+    )]
+    /// CHECK: optional keeper registry, checked by can_sign_for_keeper
+    pub keeper_registry: AccountInfo<'info>,
+
+    #[account(
+        constraint = can_sign_for_keeper(& state, & keeper_registry, & keeper_key, KeeperRole::Rewards) ?
     )]
     pub keeper_key: Signer<'info>,
 
@@ -105,6 +113,7 @@ pub fn handle_collect_rewards<'a, 'b, 'c: 'info, 'info>(
     let AccountMaps { mut oracle_map, .. } = load_maps(remaining_accounts)?;
     let mut pool = ctx.accounts.pool.load_mut()?;
     let trade_token = ctx.accounts.trade_token.load()?;
+    oracle_map.register_trade_token(&trade_token);
     let stable_trade_token = ctx.accounts.trade_token.load()?;
     let total_supply = pool.total_supply;
     let fee_reward = &pool.fee_reward;
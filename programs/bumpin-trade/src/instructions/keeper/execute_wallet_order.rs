@@ -1,9 +1,18 @@
+use crate::errors::BumpErrorCode;
 use crate::instructions::constraints::*;
-use crate::instructions::ExecuteOrderParams;
+use crate::instructions::keeper::pay_execution_fee;
+use crate::instructions::{calculator, ExecuteOrderParams};
+use crate::math::safe_math::SafeMath;
 use crate::processor::optional_accounts::{load_maps, AccountMaps};
 use crate::processor::position_processor;
-use crate::state::state::State;
+use crate::state::bump_events::{AddOrDeleteUserOrderEvent, TwapOrderSliceEvent};
+use crate::state::infrastructure::user_order::{OrderStatus, OrderType};
+use crate::state::keeper_registry::KeeperRole;
+use crate::state::order::Order;
+use crate::state::order_map::OrderMap;
+use crate::state::state::{PauseOperation, State};
 use crate::state::User;
+use crate::validate;
 use anchor_lang::prelude::*;
 use anchor_spl::token::{Token, TokenAccount};
 
@@ -26,11 +35,33 @@ pub struct ExecuteWalletOrder<'info> {
     )]
     pub user: AccountLoader<'info, User>,
 
+    #[account(
+        mut,
+        seeds = [b"order", user.key().as_ref(), params.order_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub order: AccountLoader<'info, Order>,
+
+    #[account(
+        mut,
+        address = params.user_authority_key,
+    )]
+    /// CHECK: receives the rent of the executed order, closed once it is done
+    pub user_authority: AccountInfo<'info>,
+
     #[account(mut)]
     pub user_token_account: Account<'info, TokenAccount>,
 
     #[account(
-        constraint = state.keeper_key.eq(& keeper_key.key())
+        seeds = [b"keeper_registry".as_ref()],
+        bump,
+    )]
+    /// CHECK: optional keeper registry, checked by can_sign_for_keeper
+    pub keeper_registry: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = can_sign_for_keeper(& state, & keeper_registry, & keeper_key, KeeperRole::ExecuteOrder) ?
     )]
     pub keeper_key: Signer<'info>,
 
@@ -46,19 +77,40 @@ pub struct ExecuteWalletOrder<'info> {
 
 pub fn handle_execute_wallet_order<'a, 'b, 'c: 'info, 'info>(
     ctx: Context<'a, 'b, 'c, 'info, ExecuteWalletOrder<'c>>,
-    params: ExecuteOrderParams,
+    _params: ExecuteOrderParams,
 ) -> Result<()> {
+    ctx.accounts.state.validate_operation_not_paused(PauseOperation::ExecuteOrder)?;
     let user = &mut ctx.accounts.user.load_mut()?;
-    let order = user.orders[user.get_user_order_index(params.order_id)?];
+    let mut order = ctx.accounts.order.load()?.order;
+    validate!(order.status.eq(&OrderStatus::USING), BumpErrorCode::InvalidParam)?;
     let remaining_accounts = ctx.remaining_accounts;
     let AccountMaps { trade_token_map, mut oracle_map, market_map, pool_map, vault_map, .. } =
         load_maps(remaining_accounts)?;
+    let mut order_map = OrderMap::load(remaining_accounts, user)?;
+    order_map.executing_order_id = Some(order.order_id);
+    //a filled take-profit / stop-loss cancels the other leg, if it is still open it must be passed
+    if order.get_linked_order_id()?.is_some() {
+        order_map.validate_all_orders_loaded(user, order_map.executing_order_id)?;
+    }
+    let position_key = user.get_position_key(
+        order.symbol,
+        order.is_portfolio_margin,
+        order.is_long_position(),
+        ctx.program_id,
+    )?;
+    let pre_position_size = user.get_position_size(&position_key);
     let state_account = &ctx.accounts.state;
     let user_token_account = &ctx.accounts.user_token_account;
     let bump_signer_account_info = &ctx.accounts.bump_signer;
     let token_program = &ctx.accounts.token_program;
 
-    position_processor::handle_execute_order(
+    //a twap order fills one slice per execution
+    let execute_order = match order.order_type {
+        OrderType::TWAP => order.get_twap_slice()?,
+        _ => order,
+    };
+    //the order stays open when the price tripped the circuit breaker
+    if !position_processor::handle_execute_order(
         user,
         &market_map,
         &pool_map,
@@ -70,7 +122,47 @@ pub fn handle_execute_wallet_order<'a, 'b, 'c: 'info, 'info>(
         ctx.program_id,
         &trade_token_map,
         &mut oracle_map,
-        &order,
-    )?;
+        &mut order_map,
+        &execute_order,
+    )? {
+        return Ok(());
+    }
+    //the keeper is only paid when the order fills, an unfilled reduce only order is refunded
+    let filled = user.get_position_size(&position_key) != pre_position_size;
+    if filled {
+        pay_execution_fee(
+            &ctx.accounts.order.to_account_info(),
+            &ctx.accounts.keeper_key.to_account_info(),
+            execute_order.execution_fee,
+        )?;
+    }
+    //a reduce only twap with no position left is canceled like any reduce only order, another
+    //slice that fills nothing is not counted and the order stays open for the next execution
+    let position_closed = order.reduce_only && user.get_position_size(&position_key) == 0u128;
+    if order.order_type.eq(&OrderType::TWAP) && !position_closed {
+        if !filled {
+            return Ok(());
+        }
+        order.record_twap_slice(&execute_order, calculator::current_time())?;
+        if !order.is_twap_completed() {
+            ctx.accounts.order.load_mut()?.order = order;
+            emit!(TwapOrderSliceEvent {
+                user_key: user.key,
+                order,
+                slice_size: execute_order.order_size
+            });
+            return Ok(());
+        }
+    }
+    order_map.cancel_linked_order(user, &order)?;
+    if order.has_bracket_orders() {
+        order_map.activate_bracket_orders(
+            &order,
+            user.get_position_size(&position_key).safe_sub(pre_position_size)?,
+        )?;
+    }
+    emit!(AddOrDeleteUserOrderEvent { user_key: user.key, order, is_add: false });
+    user.remove_open_order()?;
+    ctx.accounts.order.close(ctx.accounts.user_authority.to_account_info())?;
     Ok(())
 }
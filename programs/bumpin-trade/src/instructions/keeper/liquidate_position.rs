@@ -11,15 +11,17 @@ use crate::math::safe_math::SafeMath;
 use crate::processor::optional_accounts::{load_maps, AccountMaps};
 use crate::processor::position_processor;
 use crate::processor::position_processor::DecreasePositionParams;
+use crate::state::bump_events::{LiquidationEvent, UpdateUserPositionEvent};
 use crate::state::infrastructure::user_position::UserPosition;
-use crate::state::market::Market;
+use crate::state::market::{MarkPriceUsage, Market};
 use crate::state::oracle_map::OracleMap;
+use crate::state::order_map::OrderMap;
 use crate::state::pool::Pool;
-use crate::state::state::State;
+use crate::state::state::{PauseOperation, State};
 use crate::state::trade_token::TradeToken;
-use crate::state::user::User;
-use crate::utils::pda;
-use crate::utils::pda::generate_position_key;
+use crate::state::trade_token_map::TradeTokenMap;
+use crate::state::user::{User, UserTokenUpdateReason};
+use crate::utils::{pda, token};
 use crate::validate;
 
 #[derive(Accounts)]
@@ -29,7 +31,6 @@ use crate::validate;
 pub struct LiquidateCrossPosition<'info> {
     #[account(
         seeds = [b"bump_state".as_ref()],
-        has_one = keeper_key,
         bump,
     )]
     pub state: Account<'info, State>,
@@ -41,10 +42,7 @@ pub struct LiquidateCrossPosition<'info> {
     )]
     pub user: AccountLoader<'info, User>,
 
-    #[account(
-        constraint = state.keeper_key.eq(& keeper_key.key())
-    )]
-    pub keeper_key: Signer<'info>,
+    pub liquidator: Signer<'info>,
 
     #[account(
         constraint = state.bump_signer.eq(& bump_signer.key())
@@ -59,6 +57,7 @@ pub struct LiquidateCrossPosition<'info> {
 pub fn handle_liquidate_cross_position<'a, 'b, 'c: 'info, 'info>(
     ctx: Context<'a, 'b, 'c, 'info, LiquidateCrossPosition<'c>>,
 ) -> Result<()> {
+    ctx.accounts.state.validate_operation_not_paused(PauseOperation::Liquidation)?;
     let mut user = ctx.accounts.user.load_mut()?;
     let state = &ctx.accounts.state;
     let remaining_accounts = ctx.remaining_accounts;
@@ -71,164 +70,455 @@ pub fn handle_liquidate_cross_position<'a, 'b, 'c: 'info, 'info>(
         vault_map,
         ..
     } = load_maps(remaining_accounts)?;
+    oracle_map.use_liquidation_thresholds();
+    let mut order_map = OrderMap::load(remaining_accounts, &user)?;
 
-    user.cancel_all_cross_orders()?;
-
-    let mut pos_infos: Vec<PosInfos> = Vec::new();
-    for position in &user.positions {
-        //only cross margin position support
-        if !position.is_portfolio_margin {
-            continue;
-        }
-        let infos = get_position_info(position)?;
-        pos_infos.push(infos)
-    }
+    let mut pos_infos = get_cross_position_infos(&user)?;
     for pos_info in &pos_infos {
-        let market = market_map.get_ref(&pos_info.symbol)?;
-        if pos_info.is_long {}
-        let mut pool = if pos_info.is_long {
-            pool_key_map.get_mut_ref(&market.pool_key)?
-        } else {
-            pool_key_map.get_mut_ref(&market.stable_pool_key)?
+        let (pool_key, stable_pool_key) = {
+            let market = market_map.get_ref(&pos_info.symbol)?;
+            (market.pool_key, market.stable_pool_key)
         };
-        pool.update_pool_borrowing_fee_rate()?;
-
-        let market = &mut market_map.get_mut_ref(&pos_info.symbol)?;
-        let trade_token = &trade_token_map.get_trade_token_by_mint_ref(&pos_info.margin_mint)?;
-        market.update_market_funding_fee_rate(
-            &ctx.accounts.state,
-            oracle_map.get_price_data(&trade_token.oracle_key).unwrap().price,
+        pool_key_map
+            .get_mut_ref(if pos_info.is_long { &pool_key } else { &stable_pool_key })?
+            .update_pool_borrowing_fee_rate()?;
+
+        let margin_token_price = oracle_map
+            .get_price_data(
+                &trade_token_map.get_trade_token_by_mint_ref(&pos_info.margin_mint)?.oracle_key,
+            )?
+            .price;
+        let mut market = market_map.get_mut_ref(&pos_info.symbol)?;
+        //never liquidate on a print the circuit breaker rejects
+        let index_price = oracle_map.get_price_data(&market.index_mint_oracle)?.price;
+        validate!(
+            !market.is_price_deviation_exceeded(index_price)?,
+            BumpErrorCode::MarketPriceDeviationTooLarge
         )?;
+        market.update_market_funding_fee_rate(&ctx.accounts.state, margin_token_price)?;
     }
 
-    let cross_net_value = user.get_available_value(&trade_token_map, &mut oracle_map)?;
+    let (mut cross_net_value, mut total_position_mm, mut total_size) =
+        get_cross_margin_value(&mut user, &trade_token_map, &mut oracle_map, state)?;
 
-    let (
-        _total_im_usd,
-        _total_un_pnl_usd,
-        total_position_mm,
-        total_size,
-        _total_im_usd_from_portfolio,
-    ) = user.get_user_cross_position_available_value(&mut oracle_map, &trade_token_map)?;
-
-    let bankruptcy_mr = calculator::div_to_precision_i(
-        cross_net_value,
-        total_size.cast::<i128>()?,
-        SMALL_RATE_PRECISION.cast::<i128>()?,
+    //anyone can call liquidation, so it must fail unless the account is really under maintenance margin
+    validate!(
+        cross_net_value <= 0i128 || cross_net_value.abs().cast::<u128>()? <= total_position_mm,
+        BumpErrorCode::LiquidatePositionIgnore
     )?;
 
-    if cross_net_value <= 0i128 || cross_net_value.abs().cast::<u128>()? <= total_position_mm {
+    //every open order must be passed, so that no cross order keeps its hold after the liquidation
+    order_map.validate_all_orders_loaded(&user, None)?;
+    order_map.cancel_all_cross_orders(&mut user)?;
+    user.release_legacy_cross_orders()?;
+
+    //step liquidation closes positions at the mark price until the account holds its maintenance
+    //margin plus the buffer again, the account is only liquidated as a whole when that fails
+    if state.liquidation_buffer_rate > 0u32 && cross_net_value > 0i128 {
         for pos_info in &pos_infos {
-            //only cross margin position support
-            if !pos_info.is_portfolio_margin {
-                continue;
+            let (cross_net_value, total_position_mm, _) =
+                get_cross_margin_value(&mut user, &trade_token_map, &mut oracle_map, state)?;
+            let required_value =
+                get_step_liquidation_value(total_position_mm, state.liquidation_buffer_rate)?;
+            if cross_net_value <= 0i128 || cross_net_value >= required_value.cast::<i128>()? {
+                break;
             }
-
-            let market = market_map.get_ref(&pos_info.symbol)?;
-
+            let (tick_size, close_fee_rate, pool_key, stable_pool_key) = {
+                let market = market_map.get_ref(&pos_info.symbol)?;
+                (
+                    market.config.tick_size,
+                    market.config.close_fee_rate,
+                    market.pool_key,
+                    market.stable_pool_key,
+                )
+            };
             let index_price = oracle_map.get_price_data(&pos_info.index_mint)?.price;
-            let bankruptcy_price = calculator::format_to_ticker_size(
-                if pos_info.is_long {
-                    calculator::mul_small_rate_u(
-                        index_price,
-                        SMALL_RATE_PRECISION
-                            .cast::<i128>()?
-                            .safe_sub(bankruptcy_mr)?
-                            .abs()
-                            .cast::<u128>()?,
-                    )?
-                } else {
-                    calculator::mul_small_rate_u(
-                        index_price,
-                        SMALL_RATE_PRECISION
-                            .cast::<i128>()?
-                            .safe_add(bankruptcy_mr)?
-                            .abs()
-                            .cast::<u128>()?,
-                    )?
-                },
-                market.config.tick_size,
+            let execute_price = calculator::format_to_ticker_size(
+                oracle_map.get_mark_price(
+                    &pos_info.index_mint,
+                    &pos_info.symbol,
+                    MarkPriceUsage::Liquidation,
+                )?,
+                tick_size,
                 pos_info.is_long,
             )?;
+            //the liquidator reward is paid out of the equity left by the closed size
+            let (mm_rate, reward_rate) = {
+                let position = user.get_user_position_ref(&pos_info.position_key)?;
+                let equity = position
+                    .initial_margin_usd
+                    .cast::<i128>()?
+                    .safe_add(position.get_position_un_pnl_usd(execute_price)?)?;
+                (
+                    calculator::div_rate_u(position.mm_usd, position.position_size)?,
+                    if equity > 0i128 {
+                        calculator::mul_rate_u(
+                            calculator::div_rate_u(equity.cast()?, position.position_size)?,
+                            state.liquidator_reward_ratio.cast()?,
+                        )?
+                    } else {
+                        0u128
+                    },
+                )
+            };
+            let decrease_size = get_step_liquidation_size(
+                pos_info.position_size,
+                required_value.safe_sub(cross_net_value.cast::<u128>()?)?,
+                mm_rate,
+                close_fee_rate.safe_add(reward_rate)?,
+                state.liquidation_buffer_rate,
+            )?;
+            if decrease_size == 0u128 {
+                continue;
+            }
 
-            validate!(bankruptcy_price > 0, BumpErrorCode::PriceIsNotAllowed)?;
-            let mm_rate = calculator::get_mm_rate(
-                market.config.maximum_leverage,
-                state.maximum_maintenance_margin_rate,
+            let margin_pool_key = if pos_info.is_long { pool_key } else { stable_pool_key };
+            let pool_vault_key = pda::generate_pool_vault_key(
+                pool_key_map.get_ref(&margin_pool_key)?.index,
+                ctx.program_id,
             )?;
-            let liquidation_price = calculator::format_to_ticker_size(
-                if pos_info.is_long {
-                    calculator::div_rate_u(bankruptcy_price, RATE_PRECISION.safe_sub(mm_rate)?)?
-                } else {
-                    calculator::div_rate_u(bankruptcy_price, RATE_PRECISION.safe_add(mm_rate)?)?
-                },
-                market.config.tick_size,
-                pos_info.is_long,
+            let trade_token_vault_key = pda::generate_trade_token_vault_key(
+                trade_token_map.get_trade_token_by_mint_ref(&pos_info.margin_mint)?.index,
+                ctx.program_id,
             )?;
 
-            let pool = pool_key_map.get_ref(&market.pool_key)?;
-            let stable_pool = pool_key_map.get_ref(&market.stable_pool_key)?;
-            let trade_token = trade_token_map.get_trade_token_by_mint_ref(&pos_info.margin_mint)?;
-
-            position_processor::decrease_position(
+            //a step is settled like a plain close, the equity of the closed size stays in the account
+            let response = position_processor::decrease_position(
                 DecreasePositionParams {
                     order_id: 0,
-                    is_liquidation: true,
+                    is_liquidation: false,
                     is_portfolio_margin: true,
                     margin_token: pos_info.margin_mint,
-                    decrease_size: pos_info.position_size,
-                    execute_price: liquidation_price,
+                    decrease_size,
+                    execute_price,
                 },
                 &mut user,
                 market_map.get_mut_ref(&pos_info.symbol)?.deref_mut(),
-                pool_key_map.get_mut_ref(&market.pool_key)?.deref_mut(),
-                pool_key_map.get_mut_ref(&market.stable_pool_key)?.deref_mut(),
+                pool_key_map.get_mut_ref(&pool_key)?.deref_mut(),
+                pool_key_map.get_mut_ref(&stable_pool_key)?.deref_mut(),
                 &ctx.accounts.state,
                 None,
-                if pos_info.is_long {
-                    vault_map
-                        .get_account(&pda::generate_pool_vault_key(pool.index, ctx.program_id)?)?
-                } else {
-                    vault_map.get_account(&pda::generate_pool_vault_key(
-                        stable_pool.index,
-                        ctx.program_id,
-                    )?)?
-                },
+                vault_map.get_account(&pool_vault_key)?,
                 trade_token_map.get_trade_token_by_mint_ref_mut(&pos_info.margin_mint)?.deref_mut(),
-                vault_map.get_account(&pda::generate_trade_token_vault_key(
-                    trade_token.index,
-                    ctx.program_id,
-                )?)?,
+                vault_map.get_account(&trade_token_vault_key)?,
                 &ctx.accounts.bump_signer,
                 &ctx.accounts.token_program,
                 &mut oracle_map,
-                &pos_info.user_key,
+                &mut order_map,
+                &pos_info.position_key,
             )?;
+
+            //the reward stays in the account when no token account of the liquidator is passed
+            let liquidator_fee = match vault_map.get_account_by_owner_and_mint(
+                &ctx.accounts.liquidator.key(),
+                &pos_info.margin_mint,
+            ) {
+                Some(liquidator_token_account) => {
+                    let liquidator_fee = sub_cross_step_liquidator_fee(
+                        &mut user,
+                        trade_token_map
+                            .get_trade_token_by_mint_ref_mut(&pos_info.margin_mint)?
+                            .deref_mut(),
+                        response.settle_margin.max(0i128).cast::<u128>()?,
+                        state.liquidator_reward_ratio,
+                    )?;
+                    if liquidator_fee > 0u128 {
+                        token::send_from_program_vault(
+                            &ctx.accounts.token_program,
+                            vault_map.get_account(&trade_token_vault_key)?,
+                            liquidator_token_account,
+                            &ctx.accounts.bump_signer,
+                            state.bump_signer_nonce,
+                            liquidator_fee,
+                        )?;
+                    }
+                    liquidator_fee
+                },
+                None => 0u128,
+            };
+
+            emit!(LiquidationEvent {
+                user_key: user.key,
+                position_key: pos_info.position_key,
+                liquidator: ctx.accounts.liquidator.key(),
+                symbol: pos_info.symbol,
+                margin_mint_key: pos_info.margin_mint,
+                is_long: pos_info.is_long,
+                is_portfolio_margin: true,
+                position_size: decrease_size,
+                liquidation_price: execute_price,
+                index_price,
+                liquidator_fee,
+            });
+        }
+
+        (cross_net_value, total_position_mm, total_size) =
+            get_cross_margin_value(&mut user, &trade_token_map, &mut oracle_map, state)?;
+        if total_size == 0u128
+            || (cross_net_value > 0i128 && cross_net_value.cast::<u128>()? > total_position_mm)
+        {
+            return Ok(());
         }
+        pos_infos = get_cross_position_infos(&user)?;
+    }
+
+    let bankruptcy_mr = calculator::div_to_precision_i(
+        cross_net_value,
+        total_size.cast::<i128>()?,
+        SMALL_RATE_PRECISION.cast::<i128>()?,
+    )?;
+
+    for pos_info in &pos_infos {
+        let (tick_size, maximum_leverage, pool_key, stable_pool_key) = {
+            let market = market_map.get_ref(&pos_info.symbol)?;
+            (
+                market.config.tick_size,
+                market.config.maximum_leverage,
+                market.pool_key,
+                market.stable_pool_key,
+            )
+        };
+
+        let index_price = oracle_map.get_price_data(&pos_info.index_mint)?.price;
+        let bankruptcy_price = calculator::format_to_ticker_size(
+            if pos_info.is_long {
+                calculator::mul_small_rate_u(
+                    index_price,
+                    SMALL_RATE_PRECISION
+                        .cast::<i128>()?
+                        .safe_sub(bankruptcy_mr)?
+                        .abs()
+                        .cast::<u128>()?,
+                )?
+            } else {
+                calculator::mul_small_rate_u(
+                    index_price,
+                    SMALL_RATE_PRECISION
+                        .cast::<i128>()?
+                        .safe_add(bankruptcy_mr)?
+                        .abs()
+                        .cast::<u128>()?,
+                )?
+            },
+            tick_size,
+            pos_info.is_long,
+        )?;
+
+        validate!(bankruptcy_price > 0, BumpErrorCode::PriceIsNotAllowed)?;
+        let mm_rate =
+            calculator::get_mm_rate(maximum_leverage, state.maximum_maintenance_margin_rate)?;
+        let liquidation_price = calculator::format_to_ticker_size(
+            if pos_info.is_long {
+                calculator::div_rate_u(bankruptcy_price, RATE_PRECISION.safe_sub(mm_rate)?)?
+            } else {
+                calculator::div_rate_u(bankruptcy_price, RATE_PRECISION.safe_add(mm_rate)?)?
+            },
+            tick_size,
+            pos_info.is_long,
+        )?;
+
+        let margin_pool_key = if pos_info.is_long { pool_key } else { stable_pool_key };
+        let (pool_vault_key, pre_insurance_fund_amount) = {
+            let margin_pool = pool_key_map.get_ref(&margin_pool_key)?;
+            (
+                pda::generate_pool_vault_key(margin_pool.index, ctx.program_id)?,
+                margin_pool.insurance_fund_amount,
+            )
+        };
+        let trade_token_vault_key = pda::generate_trade_token_vault_key(
+            trade_token_map.get_trade_token_by_mint_ref(&pos_info.margin_mint)?.index,
+            ctx.program_id,
+        )?;
+
+        position_processor::decrease_position(
+            DecreasePositionParams {
+                order_id: 0,
+                is_liquidation: true,
+                is_portfolio_margin: true,
+                margin_token: pos_info.margin_mint,
+                decrease_size: pos_info.position_size,
+                execute_price: liquidation_price,
+            },
+            &mut user,
+            market_map.get_mut_ref(&pos_info.symbol)?.deref_mut(),
+            pool_key_map.get_mut_ref(&pool_key)?.deref_mut(),
+            pool_key_map.get_mut_ref(&stable_pool_key)?.deref_mut(),
+            &ctx.accounts.state,
+            None,
+            vault_map.get_account(&pool_vault_key)?,
+            trade_token_map.get_trade_token_by_mint_ref_mut(&pos_info.margin_mint)?.deref_mut(),
+            vault_map.get_account(&trade_token_vault_key)?,
+            &ctx.accounts.bump_signer,
+            &ctx.accounts.token_program,
+            &mut oracle_map,
+            &mut order_map,
+            &pos_info.position_key,
+        )?;
+
+        //the reward is kept in the insurance fund when no token account of the liquidator is passed
+        let liquidator_fee = match vault_map
+            .get_account_by_owner_and_mint(&ctx.accounts.liquidator.key(), &pos_info.margin_mint)
+        {
+            Some(liquidator_token_account) => {
+                let mut margin_pool = pool_key_map.get_mut_ref(&margin_pool_key)?;
+                let liquidation_value =
+                    margin_pool.insurance_fund_amount.saturating_sub(pre_insurance_fund_amount);
+                pay_liquidator_fee(
+                    margin_pool.deref_mut(),
+                    liquidation_value,
+                    state,
+                    vault_map.get_account(&pool_vault_key)?,
+                    liquidator_token_account,
+                    &ctx.accounts.bump_signer,
+                    &ctx.accounts.token_program,
+                )?
+            },
+            None => 0u128,
+        };
+
+        emit!(LiquidationEvent {
+            user_key: user.key,
+            position_key: pos_info.position_key,
+            liquidator: ctx.accounts.liquidator.key(),
+            symbol: pos_info.symbol,
+            margin_mint_key: pos_info.margin_mint,
+            is_long: pos_info.is_long,
+            is_portfolio_margin: true,
+            position_size: pos_info.position_size,
+            liquidation_price,
+            index_price,
+            liquidator_fee,
+        });
     }
     Ok(())
 }
 
+/// Pays the liquidator its share of `liquidation_value` out of the insurance fund.
+fn pay_liquidator_fee<'info>(
+    pool: &mut Pool,
+    liquidation_value: u128,
+    state: &Account<'info, State>,
+    pool_vault: &Account<'info, TokenAccount>,
+    liquidator_token_account: &Account<'info, TokenAccount>,
+    bump_signer: &AccountInfo<'info>,
+    token_program: &Program<'info, Token>,
+) -> Result<u128> {
+    let liquidator_fee =
+        liquidation_value.safe_mul_rate(state.liquidator_reward_ratio.cast::<u128>()?)?;
+    if liquidator_fee == 0u128 {
+        return Ok(0u128);
+    }
+    pool.sub_insurance_fund(liquidator_fee)?;
+    token::send_from_program_vault(
+        token_program,
+        pool_vault,
+        liquidator_token_account,
+        bump_signer,
+        state.bump_signer_nonce,
+        liquidator_fee,
+    )?;
+    Ok(liquidator_fee)
+}
+
+/// Takes the share of the liquidator out of the equity a cross step settled to the account, the
+/// caller sends it out of the trade token vault.
+fn sub_cross_step_liquidator_fee(
+    user: &mut User,
+    trade_token: &mut TradeToken,
+    step_equity: u128,
+    liquidator_reward_ratio: u32,
+) -> BumpResult<u128> {
+    let mint = trade_token.mint_key;
+    let liquidator_fee = step_equity
+        .safe_mul_rate(liquidator_reward_ratio.cast::<u128>()?)?
+        .min(user.get_user_token_ref(&mint)?.amount);
+    if liquidator_fee == 0u128 {
+        return Ok(0u128);
+    }
+    user.sub_token_with_liability(
+        &mint,
+        trade_token,
+        liquidator_fee,
+        &UserTokenUpdateReason::Liquidation,
+    )?;
+    Ok(liquidator_fee)
+}
+
+fn get_cross_position_infos(user: &User) -> BumpResult<Vec<PosInfos>> {
+    let mut pos_infos: Vec<PosInfos> = Vec::new();
+    for position in &user.positions {
+        //only cross margin position support
+        if !position.is_portfolio_margin {
+            continue;
+        }
+        let infos = get_position_info(position)?;
+        pos_infos.push(infos)
+    }
+    Ok(pos_infos)
+}
+
 fn get_position_info(position: &UserPosition) -> BumpResult<PosInfos> {
     Ok(PosInfos {
-        is_portfolio_margin: position.is_portfolio_margin,
         symbol: position.symbol,
         index_mint: position.index_mint_oracle,
         is_long: position.is_long,
         margin_mint: position.margin_mint_key,
         position_size: position.position_size,
-        user_key: position.user_key,
+        position_key: position.position_key,
     })
 }
 
+//(cross_net_value, total_position_mm, total_size)
+fn get_cross_margin_value(
+    user: &mut User,
+    trade_token_map: &TradeTokenMap,
+    oracle_map: &mut OracleMap,
+    state: &State,
+) -> BumpResult<(i128, u128, u128)> {
+    let cross_net_value = user.get_available_value(trade_token_map, oracle_map)?;
+    let (
+        _total_im_usd,
+        _total_un_pnl_usd,
+        total_position_mm,
+        total_size,
+        _total_im_usd_from_portfolio,
+    ) = user.get_user_cross_position_available_value(oracle_map, trade_token_map)?;
+    let total_position_mm =
+        total_position_mm.safe_sub(user.get_hedged_position_mm(state.hedge_mm_offset_rate)?)?;
+    Ok((cross_net_value, total_position_mm, total_size))
+}
+
+/// The value a step liquidation restores: the maintenance margin plus the liquidation buffer.
+fn get_step_liquidation_value(mm_usd: u128, liquidation_buffer_rate: u32) -> BumpResult<u128> {
+    calculator::mul_rate_u(mm_usd, RATE_PRECISION.safe_add(liquidation_buffer_rate.cast()?)?)
+}
+
+/// Size to close for the remaining value to cover the missing value, every closed usd frees its
+/// maintenance margin with the buffer and costs `cost_rate` of equity. Returns 0 when closing
+/// does not free more than it costs.
+fn get_step_liquidation_size(
+    position_size: u128,
+    missing_value: u128,
+    mm_rate: u128,
+    cost_rate: u128,
+    liquidation_buffer_rate: u32,
+) -> BumpResult<u128> {
+    let freed_rate =
+        calculator::mul_rate_u(mm_rate, RATE_PRECISION.safe_add(liquidation_buffer_rate.cast()?)?)?
+            .saturating_sub(cost_rate);
+    if freed_rate == 0u128 {
+        return Ok(0u128);
+    }
+    Ok(calculator::div_rate_u(missing_value, freed_rate)?.safe_add(1u128)?.min(position_size))
+}
+
 struct PosInfos {
-    pub is_portfolio_margin: bool,
     pub symbol: [u8; 32],
     pub index_mint: Pubkey,
     pub is_long: bool,
     pub margin_mint: Pubkey,
     pub position_size: u128,
-    pub user_key: Pubkey,
+    pub position_key: Pubkey,
 }
 
 #[derive(Accounts)]
@@ -238,7 +528,6 @@ struct PosInfos {
 pub struct LiquidateIsolatePosition<'info> {
     #[account(
         seeds = [b"bump_state".as_ref()],
-        has_one = keeper_key,
         bump,
     )]
     pub state: Account<'info, State>,
@@ -308,9 +597,13 @@ pub struct LiquidateIsolatePosition<'info> {
     pub trade_token_vault: Account<'info, TokenAccount>,
 
     #[account(
-        constraint = state.keeper_key.eq(& keeper_key.key())
+        mut,
+        constraint = liquidator_token_account.owner.eq(& liquidator.key())
+        && (pool_vault.mint.eq(& liquidator_token_account.mint) || stable_pool_vault.mint.eq(& liquidator_token_account.mint)),
     )]
-    pub keeper_key: Signer<'info>,
+    pub liquidator_token_account: Account<'info, TokenAccount>,
+
+    pub liquidator: Signer<'info>,
 
     #[account(
         constraint = state.bump_signer.eq(& bump_signer.key())
@@ -325,11 +618,16 @@ pub fn handle_liquidate_isolate_position<'a, 'b, 'c: 'info, 'info>(
     ctx: Context<'a, 'b, 'c, 'info, LiquidateIsolatePosition>,
     params: LiquidateIsolatePositionParams,
 ) -> Result<()> {
+    ctx.accounts.state.validate_operation_not_paused(PauseOperation::Liquidation)?;
     let mut user = ctx.accounts.user.load_mut()?;
     let remaining_accounts = ctx.remaining_accounts;
     let mut market = ctx.accounts.market.load_mut()?;
     let mut trade_token = ctx.accounts.trade_token.load_mut()?;
     let mut oracle_map = OracleMap::load(remaining_accounts)?;
+    oracle_map.use_liquidation_thresholds();
+    let mut order_map = OrderMap::load(remaining_accounts, &user)?;
+    oracle_map.register_market(&market);
+    oracle_map.register_trade_token(&trade_token);
     let mut base_token_pool = ctx.accounts.pool.load_mut()?;
     let mut stable_pool = ctx.accounts.stable_pool.load_mut()?;
     let position_key = params.position_key;
@@ -347,26 +645,87 @@ pub fn handle_liquidate_isolate_position<'a, 'b, 'c: 'info, 'info>(
     let position = user.get_user_position_ref(&position_key)?;
     validate!(!position.is_portfolio_margin, BumpErrorCode::OnlyIsolatePositionAllowed)?;
 
-    let index_price = oracle_map.get_price_data(&position.index_mint_oracle)?;
-    msg!("===========handle_liquidate_isolate_position, index_price:{}", index_price.price);
+    let index_price = oracle_map.get_price_data(&position.index_mint_oracle)?.price;
+    msg!("===========handle_liquidate_isolate_position, index_price:{}", index_price);
+    //never liquidate on a print the circuit breaker rejects
+    validate!(
+        !market.is_price_deviation_exceeded(index_price)?,
+        BumpErrorCode::MarketPriceDeviationTooLarge
+    )?;
+    let liquidation_index_price =
+        market.get_mark_price(index_price, MarkPriceUsage::Liquidation)?;
     if liquidation_price == 0u128
-        || index_price.price == 0u128
-        || (is_long && index_price.price > liquidation_price)
-        || (!is_long && index_price.price < liquidation_price)
+        || liquidation_index_price == 0u128
+        || (is_long && liquidation_index_price > liquidation_price)
+        || (!is_long && liquidation_index_price < liquidation_price)
     {
         Err(BumpErrorCode::LiquidatePositionIgnore)?;
     }
 
+    validate!(
+        ctx.accounts.liquidator_token_account.mint.eq(&margin_mint),
+        BumpErrorCode::TokenNotMatch
+    )?;
+
+    let state = &ctx.accounts.state;
+    let margin_token_price = oracle_map.get_price_data(&trade_token.oracle_key)?.price;
+    //step liquidation closes at the mark price just the size that brings the position back above
+    //its maintenance margin plus the buffer, the equity left by the closed size stays with it
+    let step_size = if state.liquidation_buffer_rate > 0u32 {
+        let position = user.get_user_position_ref(&position_key)?;
+        let equity = position
+            .initial_margin_usd
+            .cast::<i128>()?
+            .safe_add(position.get_position_un_pnl_usd(liquidation_index_price)?)?
+            .safe_sub(position.get_position_fee(
+                &market,
+                if is_long { &base_token_pool } else { &stable_pool },
+                margin_token_price,
+                trade_token.decimals,
+            )?)?;
+        if equity > 0i128 {
+            let equity = equity.cast::<u128>()?;
+            //the liquidator reward is paid out of the equity left by the closed size
+            let cost_rate = market.config.close_fee_rate.safe_add(calculator::mul_rate_u(
+                calculator::div_rate_u(equity, position_size)?,
+                state.liquidator_reward_ratio.cast()?,
+            )?)?;
+            get_step_liquidation_size(
+                position_size,
+                get_step_liquidation_value(position.mm_usd, state.liquidation_buffer_rate)?
+                    .saturating_sub(equity),
+                calculator::div_rate_u(position.mm_usd, position_size)?,
+                cost_rate,
+                state.liquidation_buffer_rate,
+            )?
+        } else {
+            0u128
+        }
+    } else {
+        0u128
+    };
+    let is_step = step_size > 0u128 && step_size < position_size;
+    let (decrease_size, execute_price) = if is_step {
+        (step_size, liquidation_index_price)
+    } else {
+        (position_size, liquidation_price)
+    };
+
     let symbol = market.symbol;
     let user_key = user.key;
-    position_processor::decrease_position(
+    let pre_insurance_fund_amount = if is_long {
+        base_token_pool.insurance_fund_amount
+    } else {
+        stable_pool.insurance_fund_amount
+    };
+    let response = position_processor::decrease_position(
         DecreasePositionParams {
             order_id: 0,
             is_liquidation: true,
             is_portfolio_margin: false,
             margin_token: margin_mint,
-            decrease_size: position_size,
-            execute_price: liquidation_price,
+            decrease_size,
+            execute_price,
         },
         &mut user,
         &mut market,
@@ -380,8 +739,82 @@ pub fn handle_liquidate_isolate_position<'a, 'b, 'c: 'info, 'info>(
         &ctx.accounts.bump_signer,
         &ctx.accounts.token_program,
         &mut oracle_map,
-        &generate_position_key(&user_key, symbol, false, ctx.program_id)?,
+        &mut order_map,
+        &position_key,
     )?;
+
+    //a step pays the liquidator out of the equity of the closed size and returns the rest to the
+    //position, a full liquidation out of what the insurance fund received
+    let margin_pool = if is_long { base_token_pool.deref_mut() } else { stable_pool.deref_mut() };
+    let liquidation_value = if is_step {
+        response.settle_margin.max(0i128).cast::<u128>()?
+    } else {
+        margin_pool.insurance_fund_amount.saturating_sub(pre_insurance_fund_amount)
+    };
+    let liquidator_fee = pay_liquidator_fee(
+        margin_pool,
+        liquidation_value,
+        &ctx.accounts.state,
+        if is_long { &ctx.accounts.pool_vault } else { &ctx.accounts.stable_pool_vault },
+        &ctx.accounts.liquidator_token_account,
+        &ctx.accounts.bump_signer,
+        &ctx.accounts.token_program,
+    )?;
+
+    if is_step {
+        add_step_liquidation_margin(
+            user.get_user_position_mut_ref(&position_key)?,
+            if is_long { base_token_pool.deref_mut() } else { stable_pool.deref_mut() },
+            liquidation_value.safe_sub(liquidator_fee)?,
+            trade_token.decimals,
+            margin_token_price,
+        )?;
+    }
+
+    emit!(LiquidationEvent {
+        user_key,
+        position_key,
+        liquidator: ctx.accounts.liquidator.key(),
+        symbol,
+        margin_mint_key: margin_mint,
+        is_long,
+        is_portfolio_margin: false,
+        position_size: decrease_size,
+        liquidation_price: execute_price,
+        index_price,
+        liquidator_fee,
+    });
+    Ok(())
+}
+
+/// The equity left by the closed size of an isolated step liquidation is kept by the insurance
+/// fund on decrease, what the liquidator was not paid is moved back to the margin of the
+/// remaining position.
+fn add_step_liquidation_margin(
+    position: &mut UserPosition,
+    pool: &mut Pool,
+    add_margin: u128,
+    margin_token_decimals: u16,
+    margin_token_price: u128,
+) -> BumpResult {
+    if add_margin == 0u128 {
+        return Ok(());
+    }
+    let pre_position = *position;
+    pool.sub_insurance_fund(add_margin)?;
+    position.add_initial_margin(add_margin)?;
+    position.add_initial_margin_usd(calculator::token_to_usd_u(
+        add_margin,
+        margin_token_decimals,
+        margin_token_price,
+    )?)?;
+    position.set_leverage(
+        calculator::div_rate_u(position.position_size, position.initial_margin_usd)?.cast()?,
+    )?;
+    let un_hold_amount = add_margin.min(position.hold_pool_amount);
+    position.sub_hold_pool_amount(un_hold_amount)?;
+    pool.update_pnl_and_un_hold_pool_amount(un_hold_amount, 0i128, 0u128, None)?;
+    emit!(UpdateUserPositionEvent { pre_position, position: *position });
     Ok(())
 }
 
@@ -420,3 +853,27 @@ fn cal_liquidation_price(
         liquidation_price,
     ))
 }
+
+#[cfg(test)]
+mod test {
+    use crate::instructions::keeper::liquidate_position::{
+        get_step_liquidation_size, get_step_liquidation_value,
+    };
+
+    #[test]
+    fn test_get_step_liquidation_value() {
+        assert_eq!(get_step_liquidation_value(1_000, 0).unwrap(), 1_000);
+        assert_eq!(get_step_liquidation_value(1_000, 10_000).unwrap(), 1_100);
+    }
+
+    #[test]
+    fn test_get_step_liquidation_size() {
+        //1% mm with a 10% buffer frees 1.1% per closed usd, 0.1% is spent on fees
+        assert_eq!(get_step_liquidation_size(1_000_000, 50, 1_000, 100, 10_000).unwrap(), 5_001);
+        assert_eq!(get_step_liquidation_size(3_000, 50, 1_000, 100, 10_000).unwrap(), 3_000);
+        assert_eq!(get_step_liquidation_size(1_000_000, 0, 1_000, 100, 10_000).unwrap(), 1);
+        //closing does not help when it costs what it frees
+        assert_eq!(get_step_liquidation_size(1_000_000, 50, 1_000, 1_100, 10_000).unwrap(), 0);
+        assert_eq!(get_step_liquidation_size(1_000_000, 50, 1_000, 2_000, 10_000).unwrap(), 0);
+    }
+}
@@ -4,6 +4,8 @@ pub mod execute_portfolio_order;
 pub mod execute_wallet_order;
 pub mod liquidate_position;
 pub mod rebalance;
+pub mod update_mark_price;
+pub mod update_trailing_stop;
 
 pub use adl::*;
 use anchor_lang::prelude::*;
@@ -12,6 +14,8 @@ pub use execute_portfolio_order::*;
 pub use execute_wallet_order::*;
 pub use liquidate_position::*;
 pub use rebalance::*;
+pub use update_mark_price::*;
+pub use update_trailing_stop::*;
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Eq, PartialEq)]
 pub struct LiquidateIsolatePositionParams {
@@ -38,3 +42,18 @@ pub struct ExecuteOrderParams {
     order_id: u64,
     user_authority_key: Pubkey,
 }
+
+/// Pays the execution fee escrowed in a filled order to the executing keeper, the rent left in
+/// the order account goes back to the user when it is closed.
+fn pay_execution_fee<'info>(
+    order_account: &AccountInfo<'info>,
+    keeper: &AccountInfo<'info>,
+    execution_fee: u64,
+) -> Result<()> {
+    if execution_fee == 0 {
+        return Ok(());
+    }
+    order_account.sub_lamports(execution_fee)?;
+    keeper.add_lamports(execution_fee)?;
+    Ok(())
+}
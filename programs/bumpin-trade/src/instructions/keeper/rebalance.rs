@@ -1,4 +1,6 @@
+use crate::instructions::constraints::can_sign_for_keeper;
 use crate::processor::rebalance_processor;
+use crate::state::keeper_registry::KeeperRole;
 use crate::state::pool::Pool;
 use crate::state::state::State;
 use crate::state::trade_token::TradeToken;
@@ -66,6 +68,18 @@ pub struct AutoRebalance<'info> {
     )]
     pub trade_token_vault: Box<Account<'info, TokenAccount>>,
 
+    #[account(
+        seeds = [b"keeper_registry".as_ref()],
+        bump,
+    )]
+    /// CHECK: optional keeper registry, checked by can_sign_for_keeper
+    pub keeper_registry: AccountInfo<'info>,
+
+    #[account(
+        constraint = can_sign_for_keeper(& state, & keeper_registry, & keeper_key, KeeperRole::Rebalance) ?
+    )]
+    pub keeper_key: Signer<'info>,
+
     #[account(
         constraint = state.bump_signer.eq(& bump_signer.key())
     )]
@@ -113,6 +127,18 @@ pub struct RewardsRebalance<'info> {
     )]
     pub trade_token_vault: Box<Account<'info, TokenAccount>>,
 
+    #[account(
+        seeds = [b"keeper_registry".as_ref()],
+        bump,
+    )]
+    /// CHECK: optional keeper registry, checked by can_sign_for_keeper
+    pub keeper_registry: AccountInfo<'info>,
+
+    #[account(
+        constraint = can_sign_for_keeper(& state, & keeper_registry, & keeper_key, KeeperRole::Rebalance) ?
+    )]
+    pub keeper_key: Signer<'info>,
+
     #[account(
         constraint = state.bump_signer.eq(& bump_signer.key())
     )]
@@ -0,0 +1,36 @@
+use anchor_lang::prelude::*;
+
+use crate::state::market::Market;
+use crate::state::oracle_map::OracleMap;
+
+#[derive(Accounts)]
+#[instruction(
+    params: UpdateMarkPriceParams,
+)]
+pub struct UpdateMarkPrice<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", params.market_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub market: AccountLoader<'info, Market>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub struct UpdateMarkPriceParams {
+    pub market_index: u16,
+}
+
+//permissionless crank: the ema is time weighted, so extra updates can not move it faster
+//it also trips or clears the price circuit breaker when no order is being executed
+pub fn handle_update_mark_price<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, UpdateMarkPrice<'info>>,
+    _params: UpdateMarkPriceParams,
+) -> Result<()> {
+    let mut market = ctx.accounts.market.load_mut()?;
+    let mut oracle_map = OracleMap::load(ctx.remaining_accounts)?;
+    oracle_map.register_market(&market);
+    let index_price_data = *oracle_map.get_price_data(&market.index_mint_oracle)?;
+    market.update_mark_price(&index_price_data)?;
+    Ok(())
+}
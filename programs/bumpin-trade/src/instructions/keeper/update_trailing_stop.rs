@@ -0,0 +1,72 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::BumpErrorCode;
+use crate::state::infrastructure::user_order::{OrderStatus, OrderType};
+use crate::state::market::{MarkPriceUsage, Market};
+use crate::state::oracle_map::OracleMap;
+use crate::state::order::Order;
+use crate::state::user::User;
+use crate::validate;
+
+#[derive(Accounts)]
+#[instruction(
+    params: UpdateTrailingStopParams,
+)]
+pub struct UpdateTrailingStop<'info> {
+    #[account(
+        seeds = [b"user", params.user_authority_key.as_ref()],
+        bump,
+    )]
+    pub user: AccountLoader<'info, User>,
+
+    #[account(
+        mut,
+        seeds = [b"order", user.key().as_ref(), params.order_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub order: AccountLoader<'info, Order>,
+
+    #[account(
+        seeds = [b"market", params.market_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub market: AccountLoader<'info, Market>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub struct UpdateTrailingStopParams {
+    pub order_id: u64,
+    pub market_index: u16,
+    pub user_authority_key: Pubkey,
+}
+
+//permissionless crank: the extreme is only ever moved by an oracle print read here, newer than
+//the last one folded in, so a keeper can skip updates but never fake a high or low
+pub fn handle_update_trailing_stop<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, UpdateTrailingStop<'info>>,
+    _params: UpdateTrailingStopParams,
+) -> Result<()> {
+    let market = ctx.accounts.market.load()?;
+    let mut order = ctx.accounts.order.load_mut()?;
+    let user_order = &mut order.order;
+    validate!(
+        user_order.order_type.eq(&OrderType::TRAILING_STOP)
+            && user_order.status.eq(&OrderStatus::USING)
+            && user_order.symbol == market.symbol,
+        BumpErrorCode::InvalidParam
+    )?;
+
+    let mut oracle_map = OracleMap::load(ctx.remaining_accounts)?;
+    oracle_map.register_market(&market);
+    let index_price_data = oracle_map.get_price_data(&market.index_mint_oracle)?;
+    //never track a print the circuit breaker rejects
+    validate!(
+        !market.is_price_deviation_exceeded(index_price_data.price)?,
+        BumpErrorCode::MarketPriceDeviationTooLarge
+    )?;
+    user_order.update_trailing_extreme(
+        market.get_mark_price(index_price_data.price, MarkPriceUsage::Trigger)?,
+        index_price_data.publish_time,
+    )?;
+    Ok(())
+}
@@ -0,0 +1,71 @@
+pub mod portfolio_amend_order;
+pub mod wallet_amend_order;
+
+use crate::errors::{BumpErrorCode, BumpResult};
+use crate::instructions::PlaceOrderParams;
+use crate::state::infrastructure::user_order::{OrderStatus, OrderType, PositionSide, UserOrder};
+use crate::validate;
+use anchor_lang::prelude::*;
+pub use portfolio_amend_order::*;
+pub use wallet_amend_order::*;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub struct AmendOrderParams {
+    pub pool_index: u16,
+    pub order_id: u64,
+    pub size: u128,
+    pub order_margin: u128,
+    pub trigger_price: u128,
+    pub acceptable_price: u128,
+}
+
+/// Rebuilds the place params of a resting order with the amended values, so the amended order
+/// goes through the same validation as a new one.
+fn get_amended_place_params(
+    order: &UserOrder,
+    params: &AmendOrderParams,
+) -> BumpResult<PlaceOrderParams> {
+    validate!(
+        order.status.eq(&OrderStatus::USING)
+            && !order.order_type.eq(&OrderType::MARKET)
+            && (order.position_side.eq(&PositionSide::INCREASE) || params.order_margin == 0u128),
+        BumpErrorCode::InvalidParam
+    )?;
+    //bracket orders carry their parent prices, those are only checked on the parent
+    let (take_profit_price, stop_loss_price) = if order.is_bracket {
+        (0u128, 0u128)
+    } else {
+        (order.take_profit_price, order.stop_loss_price)
+    };
+    Ok(PlaceOrderParams {
+        symbol: order.symbol,
+        size: params.size,
+        order_margin: params.order_margin,
+        leverage: order.leverage,
+        trigger_price: params.trigger_price,
+        acceptable_price: params.acceptable_price,
+        place_time: order.created_at,
+        is_portfolio_margin: order.is_portfolio_margin,
+        is_native_token: false,
+        order_side: order.order_side,
+        position_side: order.position_side,
+        order_type: order.order_type,
+        stop_type: order.stop_type,
+        time_in_force: order.time_in_force,
+        expires_at: order.expires_at,
+        take_profit_price,
+        stop_loss_price,
+        trailing_callback: order.trailing_callback,
+        trailing_callback_type: order.trailing_callback_type,
+        reduce_only: order.reduce_only,
+        twap_slice_count: order.twap_slice_count,
+        twap_interval: order.twap_interval,
+    })
+}
+
+fn apply_amend_params(order: &mut UserOrder, params: &AmendOrderParams) {
+    order.order_size = params.size;
+    order.order_margin = params.order_margin;
+    order.trigger_price = params.trigger_price;
+    order.acceptable_price = params.acceptable_price;
+}
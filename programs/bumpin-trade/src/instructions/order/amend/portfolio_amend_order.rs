@@ -0,0 +1,104 @@
+use std::ops::{Deref, DerefMut};
+
+use anchor_lang::prelude::*;
+
+use crate::errors::BumpErrorCode;
+use crate::instructions::amend::{apply_amend_params, get_amended_place_params};
+use crate::instructions::constraints::*;
+use crate::instructions::place::validate_place_order;
+use crate::instructions::AmendOrderParams;
+use crate::math::safe_math::SafeMath;
+use crate::processor::optional_accounts::{load_maps, AccountMaps};
+use crate::state::bump_events::AmendUserOrderEvent;
+use crate::state::infrastructure::user_order::PositionSide;
+use crate::state::order::Order;
+use crate::state::pool::Pool;
+use crate::state::state::{PauseOperation, State};
+use crate::state::user::User;
+use crate::validate;
+
+#[derive(Accounts)]
+#[instruction(
+    params: AmendOrderParams,
+)]
+pub struct PortfolioAmendOrder<'info> {
+    #[account(
+        seeds = [b"bump_state".as_ref()],
+        bump,
+    )]
+    pub state: Box<Account<'info, State>>,
+
+    #[account(
+        mut,
+        seeds = [b"user", authority.key().as_ref()],
+        bump,
+        constraint = can_sign_for_user(& user, & authority) ? && is_normal(& user) ?,
+    )]
+    pub user: AccountLoader<'info, User>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"order", user.key().as_ref(), params.order_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub order: AccountLoader<'info, Order>,
+
+    #[account(
+        seeds = [b"pool".as_ref(), params.pool_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+}
+
+#[track_caller]
+pub fn handle_amend_portfolio_order<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, PortfolioAmendOrder<'c>>,
+    params: AmendOrderParams,
+) -> Result<()> {
+    ctx.accounts.state.validate_operation_not_paused(PauseOperation::PlaceOrder)?;
+    let AccountMaps { mut oracle_map, market_map, .. } = load_maps(ctx.remaining_accounts)?;
+    let mut user = ctx.accounts.user.load_mut()?;
+    let mut order = ctx.accounts.order.load_mut()?;
+    let pre_order = order.order;
+    let pool = ctx.accounts.pool.load()?;
+    validate!(
+        pre_order.is_portfolio_margin
+            && params.pool_index == pool.index
+            && pre_order.margin_mint_key.eq(&pool.mint_key),
+        BumpErrorCode::InvalidParam
+    )?;
+    let place_params = get_amended_place_params(&pre_order, &params)?;
+    let market = market_map.get_ref(&pre_order.symbol)?;
+    let token_price = oracle_map
+        .get_price_data(&market.index_mint_oracle)
+        .map_err(|_e| BumpErrorCode::OracleNotFound)?
+        .price;
+    validate!(
+        validate_place_order(
+            &place_params,
+            &pre_order.margin_mint_key,
+            &market,
+            pool.deref(),
+            &ctx.accounts.state,
+            token_price
+        )?,
+        BumpErrorCode::InvalidParam
+    )?;
+
+    if pre_order.position_side.eq(&PositionSide::INCREASE) {
+        //hold usd follows the amended margin
+        if params.order_margin > pre_order.order_margin {
+            user.deref_mut()
+                .add_order_hold_in_usd(params.order_margin.safe_sub(pre_order.order_margin)?)?;
+        } else if params.order_margin < pre_order.order_margin {
+            user.deref_mut()
+                .sub_order_hold_in_usd(pre_order.order_margin.safe_sub(params.order_margin)?)?;
+        }
+    }
+
+    apply_amend_params(&mut order.order, &params);
+    emit!(AmendUserOrderEvent { user_key: user.key, pre_order, order: order.order });
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use crate::instructions::constraints::*;
+use crate::instructions::CancelAllOrdersParams;
+use crate::processor::optional_accounts::{load_maps, AccountMaps};
+use crate::processor::position_processor::use_base_token;
+use crate::state::infrastructure::user_order::PositionSide;
+use crate::state::order_map::OrderMap;
+use crate::state::state::State;
+use crate::state::user::User;
+use crate::utils::token;
+use anchor_lang::prelude::*;
+use anchor_spl::token::Token;
+
+#[derive(Accounts)]
+pub struct CancelAllOrders<'info> {
+    #[account(
+        seeds = [b"bump_state".as_ref()],
+        bump,
+    )]
+    pub state: Box<Account<'info, State>>,
+
+    #[account(
+        mut,
+        seeds = [b"user", authority.key().as_ref()],
+        bump,
+        constraint = can_sign_for_user(& user, & authority) ? && is_normal(& user) ?,
+    )]
+    pub user: AccountLoader<'info, User>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        constraint = state.bump_signer.eq(& bump_signer.key())
+    )]
+    /// CHECK: ?
+    pub bump_signer: AccountInfo<'info>,
+
+    #[account(address = Token::id())]
+    pub token_program: Program<'info, Token>,
+}
+
+/// Cancels the open orders that match the filters, every open order of the user is passed in the
+/// remaining accounts. Wallet increase orders need their market, pool, pool vault and user token
+/// account passed as well to refund the escrowed margin.
+#[track_caller]
+pub fn handle_cancel_all_orders<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, CancelAllOrders<'c>>,
+    params: CancelAllOrdersParams,
+) -> Result<()> {
+    let remaining_accounts = ctx.remaining_accounts;
+    let AccountMaps { market_map, pool_map, vault_map, .. } = load_maps(remaining_accounts)?;
+    let mut user = ctx.accounts.user.load_mut()?;
+    let mut order_map = OrderMap::load(remaining_accounts, &user)?;
+    //a canceled increase order takes its pending bracket orders along
+    order_map.validate_all_orders_loaded(&user, None)?;
+    order_map.cancel_cross_orders(&mut user, &params.symbol, &params.order_side)?;
+
+    for order in order_map.get_all_order()? {
+        if !order_map.orders.contains_key(&order.order_id) {
+            continue;
+        }
+        if order.is_portfolio_margin || !order.is_matched(&params.symbol, &params.order_side) {
+            continue;
+        }
+        if order.position_side.eq(&PositionSide::INCREASE) {
+            let market = market_map.get_ref(&order.symbol)?;
+            let pool = match use_base_token(&order.position_side, &order.order_side)? {
+                true => pool_map.get_ref(&market.pool_key)?,
+                false => pool_map.get_ref(&market.stable_pool_key)?,
+            };
+            token::send_from_program_vault(
+                &ctx.accounts.token_program,
+                vault_map.get_account(&pool.pool_vault_key)?,
+                vault_map.get_account(&order.user_token_account)?,
+                &ctx.accounts.bump_signer,
+                ctx.accounts.state.bump_signer_nonce,
+                order.order_margin,
+            )?;
+        }
+        order_map.close_order(&mut user, order.order_id)?;
+        order_map.cancel_bracket_orders(&mut user, order.order_id)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use crate::errors::BumpErrorCode;
+use crate::instructions::{calculator, CancelExpiredOrderParams};
+use crate::state::infrastructure::user_order::{OrderStatus, PositionSide};
+use crate::state::order::Order;
+use crate::state::order_map::OrderMap;
+use crate::state::pool::Pool;
+use crate::state::state::State;
+use crate::state::user::User;
+use crate::validate;
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount};
+
+#[derive(Accounts)]
+#[instruction(
+    params: CancelExpiredOrderParams,
+)]
+pub struct CancelExpiredOrder<'info> {
+    #[account(
+        seeds = [b"bump_state".as_ref()],
+        bump,
+    )]
+    pub state: Box<Account<'info, State>>,
+
+    #[account(
+        mut,
+        seeds = [b"user", params.user_authority_key.as_ref()],
+        bump,
+    )]
+    pub user: AccountLoader<'info, User>,
+
+    #[account(
+        mut,
+        seeds = [b"order", user.key().as_ref(), params.order_id.to_le_bytes().as_ref()],
+        bump,
+        close = user_authority,
+    )]
+    pub order: AccountLoader<'info, Order>,
+
+    #[account(
+        mut,
+        address = params.user_authority_key,
+    )]
+    /// CHECK: receives the rent of the canceled order
+    pub user_authority: AccountInfo<'info>,
+
+    #[account(
+        seeds = [b"pool".as_ref(), params.pool_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault".as_ref(), params.pool_index.to_le_bytes().as_ref()],
+        bump,
+        token::mint = pool.load() ?.mint_key,
+        token::authority = bump_signer
+    )]
+    pub pool_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::authority = user_authority
+    )]
+    pub user_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub keeper: Signer<'info>,
+
+    #[account(
+        constraint = state.bump_signer.eq(& bump_signer.key())
+    )]
+    /// CHECK: ?
+    pub bump_signer: AccountInfo<'info>,
+
+    #[account(address = Token::id())]
+    pub token_program: Program<'info, Token>,
+}
+
+/// Anyone can cancel an order once its `expires_at` has passed, releasing the order hold
+/// and refunding the isolate order margin to the token account it was paid from.
+#[track_caller]
+pub fn handle_cancel_expired_order<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, CancelExpiredOrder<'c>>,
+    params: CancelExpiredOrderParams,
+) -> Result<()> {
+    let mut user = ctx.accounts.user.load_mut()?;
+    let order = ctx.accounts.order.load()?.order;
+    let pool = ctx.accounts.pool.load()?;
+    if order.status.eq(&OrderStatus::INIT) {
+        return Err(BumpErrorCode::InvalidParam.into());
+    }
+    validate!(order.is_expired(calculator::current_time()), BumpErrorCode::OrderNotExpired)?;
+    //validate pool is correct
+    validate!(
+        params.pool_index == pool.index && order.margin_mint_key.eq(&pool.mint_key),
+        BumpErrorCode::InvalidParam
+    )?;
+    let user_token_account = ctx.accounts.user_token_account.as_ref();
+    if order.position_side.eq(&PositionSide::INCREASE) && !order.is_portfolio_margin {
+        validate!(
+            user_token_account
+                .is_some_and(|token_account| token_account.key().eq(&order.user_token_account)),
+            BumpErrorCode::InvalidTokenAccount
+        )?;
+    }
+    //pending bracket orders go with their increase order
+    let mut order_map = OrderMap::load(ctx.remaining_accounts, &user)?;
+    if order.has_bracket_orders() && order.status.eq(&OrderStatus::USING) {
+        order_map.validate_all_orders_loaded(&user, Some(order.order_id))?;
+    }
+    user.cancel_order(
+        &order,
+        &ctx.accounts.token_program,
+        &ctx.accounts.pool_vault,
+        user_token_account,
+        &ctx.accounts.bump_signer,
+        &ctx.accounts.state,
+    )?;
+    order_map.cancel_bracket_orders(&mut user, order.order_id)?;
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use crate::errors::BumpErrorCode;
+use crate::instructions::constraints::*;
+use crate::instructions::CancelOrderParams;
+use crate::state::bump_events::AddOrDeleteUserOrderEvent;
+use crate::state::infrastructure::user_order::{LegacyUserOrder, PositionSide};
+use crate::state::pool::Pool;
+use crate::state::state::State;
+use crate::state::user::User;
+use crate::utils::token;
+use crate::validate;
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount};
+
+#[derive(Accounts)]
+#[instruction(
+    params: CancelOrderParams,
+)]
+pub struct CancelLegacyOrder<'info> {
+    #[account(
+        seeds = [b"bump_state".as_ref()],
+        bump,
+    )]
+    pub state: Box<Account<'info, State>>,
+
+    #[account(
+        mut,
+        seeds = [b"user", authority.key().as_ref()],
+        bump,
+        constraint = can_sign_for_user(& user, & authority) ? && is_normal(& user) ?,
+    )]
+    pub user: AccountLoader<'info, User>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"pool".as_ref(), params.pool_index.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub pool: AccountLoader<'info, Pool>,
+
+    #[account(
+        mut,
+        seeds = [b"pool_vault".as_ref(), params.pool_index.to_le_bytes().as_ref()],
+        bump,
+        token::mint = pool.load() ?.mint_key,
+        token::authority = bump_signer
+    )]
+    pub pool_vault: Box<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::authority = authority
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        constraint = state.bump_signer.eq(& bump_signer.key())
+    )]
+    /// CHECK: ?
+    pub bump_signer: AccountInfo<'info>,
+
+    #[account(address = Token::id())]
+    pub token_program: Program<'info, Token>,
+}
+
+/// Cancels an order left in the legacy order array without migrating it first, the hold or the
+/// escrowed margin of an increase order is released. Legacy orders are not part of the open order
+/// count.
+#[track_caller]
+pub fn handle_cancel_legacy_order<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, CancelLegacyOrder<'c>>,
+    params: CancelOrderParams,
+) -> Result<()> {
+    let mut user = ctx.accounts.user.load_mut()?;
+    let pool = ctx.accounts.pool.load()?;
+    let index = user
+        .legacy_orders
+        .iter()
+        .position(|legacy_order| legacy_order.is_live() && legacy_order.order_id == params.order_id)
+        .ok_or(BumpErrorCode::CouldNotFindUserOrder)?;
+    let legacy_order = user.legacy_orders[index];
+    //validate pool is correct
+    validate!(
+        params.pool_index == pool.index && legacy_order.margin_mint_key.eq(&pool.mint_key),
+        BumpErrorCode::InvalidParam
+    )?;
+    if legacy_order.position_side.eq(&PositionSide::INCREASE) {
+        if legacy_order.is_portfolio_margin {
+            user.sub_order_hold_in_usd(legacy_order.order_margin)?;
+        } else {
+            token::send_from_program_vault(
+                &ctx.accounts.token_program,
+                &ctx.accounts.pool_vault,
+                &ctx.accounts.user_token_account,
+                &ctx.accounts.bump_signer,
+                ctx.accounts.state.bump_signer_nonce,
+                legacy_order.order_margin,
+            )?;
+        }
+    }
+    user.legacy_orders[index] = LegacyUserOrder::default();
+    emit!(AddOrDeleteUserOrderEvent {
+        user_key: user.key,
+        order: legacy_order.to_user_order(),
+        is_add: false
+    });
+    Ok(())
+}
@@ -1,5 +1,11 @@
+pub mod cancel_all_orders;
+pub mod cancel_expired_order;
+pub mod cancel_legacy_order;
 pub mod portfolio_cancel_order;
 pub mod wallet_cancel_order;
 
+pub use cancel_all_orders::*;
+pub use cancel_expired_order::*;
+pub use cancel_legacy_order::*;
 pub use portfolio_cancel_order::*;
 pub use wallet_cancel_order::*;
@@ -2,6 +2,8 @@ use crate::errors::BumpErrorCode;
 use crate::instructions::constraints::*;
 use crate::instructions::CancelOrderParams;
 use crate::state::infrastructure::user_order::OrderStatus;
+use crate::state::order::Order;
+use crate::state::order_map::OrderMap;
 use crate::state::pool::Pool;
 use crate::state::state::State;
 use crate::state::user::User;
@@ -28,8 +30,17 @@ pub struct WalletCancelOrder<'info> {
     )]
     pub user: AccountLoader<'info, User>,
 
+    #[account(mut)]
     pub authority: Signer<'info>,
 
+    #[account(
+        mut,
+        seeds = [b"order", user.key().as_ref(), params.order_id.to_le_bytes().as_ref()],
+        bump,
+        close = authority,
+    )]
+    pub order: AccountLoader<'info, Order>,
+
     #[account(
         seeds = [b"pool".as_ref(), params.pool_index.to_le_bytes().as_ref()],
         bump,
@@ -62,12 +73,12 @@ pub struct WalletCancelOrder<'info> {
 }
 
 #[track_caller]
-pub fn handle_wallet_cancel_order(
-    ctx: Context<WalletCancelOrder>,
+pub fn handle_wallet_cancel_order<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, WalletCancelOrder<'c>>,
     params: CancelOrderParams,
 ) -> Result<()> {
     let mut user = ctx.accounts.user.load_mut()?;
-    let order = *user.get_user_order_ref(params.order_id)?;
+    let order = ctx.accounts.order.load()?.order;
     let pool = ctx.accounts.pool.load()?;
     if order.status.eq(&OrderStatus::INIT) {
         return Err(BumpErrorCode::InvalidParam.into());
@@ -77,6 +88,11 @@ pub fn handle_wallet_cancel_order(
         params.pool_index == pool.index && order.margin_mint_key.eq(&pool.mint_key),
         BumpErrorCode::InvalidParam
     )?;
+    //pending bracket orders go with their increase order
+    let mut order_map = OrderMap::load(ctx.remaining_accounts, &user)?;
+    if order.has_bracket_orders() && order.status.eq(&OrderStatus::USING) {
+        order_map.validate_all_orders_loaded(&user, Some(order.order_id))?;
+    }
     user.cancel_order(
         &order,
         &ctx.accounts.token_program,
@@ -85,5 +101,6 @@ pub fn handle_wallet_cancel_order(
         &ctx.accounts.bump_signer,
         &ctx.accounts.state,
     )?;
+    order_map.cancel_bracket_orders(&mut user, order.order_id)?;
     Ok(())
 }
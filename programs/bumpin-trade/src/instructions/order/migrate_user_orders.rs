@@ -0,0 +1,64 @@
+use anchor_lang::prelude::*;
+
+use crate::instructions::constraints::*;
+use crate::instructions::place::create_order_account;
+use crate::state::bump_events::AddOrDeleteUserOrderEvent;
+use crate::state::infrastructure::user_order::LegacyUserOrder;
+use crate::state::user::User;
+
+#[derive(Accounts)]
+pub struct MigrateUserOrders<'info> {
+    #[account(
+        mut,
+        seeds = [b"user", authority.key().as_ref()],
+        bump,
+        constraint = can_sign_for_user(& user, & authority) ?,
+    )]
+    pub user: AccountLoader<'info, User>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Moves the live orders of the legacy order array into order accounts seeded by their ids, the
+/// order hold and the escrowed margin stay as they are. The order accounts are passed writable in
+/// the remaining accounts, the authority pays their rent.
+#[track_caller]
+pub fn handle_migrate_user_orders<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, MigrateUserOrders<'c>>,
+) -> Result<()> {
+    let mut user = ctx.accounts.user.load_mut()?;
+    let payer = ctx.accounts.authority.to_account_info();
+    let mut order_accounts = Vec::new();
+    for index in 0..user.legacy_orders.len() {
+        let legacy_order = user.legacy_orders[index];
+        if !legacy_order.is_live() {
+            continue;
+        }
+        let order_account = create_order_account(
+            &user.key,
+            legacy_order.order_id,
+            ctx.remaining_accounts,
+            &payer,
+            &ctx.accounts.system_program,
+            ctx.program_id,
+        )?;
+        let user_order = legacy_order.to_user_order();
+        {
+            let mut order_account = order_account.load_init()?;
+            order_account.user_key = user.key;
+            order_account.order = user_order;
+        }
+        user.legacy_orders[index] = LegacyUserOrder::default();
+        user.add_open_order()?;
+        emit!(AddOrDeleteUserOrderEvent { user_key: user.key, order: user_order, is_add: true });
+        order_accounts.push(order_account);
+    }
+
+    for order_account in order_accounts {
+        order_account.exit(ctx.program_id)?;
+    }
+    Ok(())
+}
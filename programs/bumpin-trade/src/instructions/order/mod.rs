@@ -1,8 +1,13 @@
+pub mod amend;
 pub mod cancel;
+pub mod migrate_user_orders;
 pub mod place;
 
+use crate::state::infrastructure::user_order::OrderSide;
+pub use amend::*;
 use anchor_lang::prelude::*;
 pub use cancel::*;
+pub use migrate_user_orders::*;
 pub use place::*;
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Eq, PartialEq)]
@@ -10,3 +15,16 @@ pub struct CancelOrderParams {
     pub pool_index: u16,
     pub order_id: u64,
 }
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Eq, PartialEq)]
+pub struct CancelExpiredOrderParams {
+    pub pool_index: u16,
+    pub order_id: u64,
+    pub user_authority_key: Pubkey,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Eq, PartialEq)]
+pub struct CancelAllOrdersParams {
+    pub symbol: Option<[u8; 32]>,
+    pub order_side: Option<OrderSide>,
+}
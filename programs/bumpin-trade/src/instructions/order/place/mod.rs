@@ -1,14 +1,30 @@
+pub mod place_orders;
 pub mod portfolio_place_order;
 pub mod wallet_place_order;
 
-use crate::errors::BumpResult;
+use crate::errors::{BumpErrorCode, BumpResult};
+use crate::instructions::calculator;
+use crate::math::constants::BPS_PRECISION;
 use crate::math::safe_math::SafeMath;
-use crate::state::infrastructure::user_order::{OrderSide, OrderType, PositionSide, StopType};
-use crate::state::market::Market;
+use crate::state::bump_events::AddOrDeleteUserOrderEvent;
+use crate::state::infrastructure::user_order::{
+    OrderSide, OrderStatus, OrderType, PositionSide, StopType, TimeInForce, TrailingCallbackType,
+    UserOrder,
+};
+use crate::state::market::{MarkPriceUsage, Market};
+use crate::state::oracle::OraclePriceData;
+use crate::state::order::Order;
 use crate::state::pool::Pool;
 use crate::state::state::State;
+use crate::state::user::User;
+use crate::traits::Size;
+use crate::{get_then_update_id, validate};
 use anchor_lang::prelude::Pubkey;
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::invoke;
+use anchor_lang::solana_program::system_instruction::transfer;
+use anchor_lang::system_program;
+pub use place_orders::*;
 pub use portfolio_place_order::*;
 pub use wallet_place_order::*;
 
@@ -27,9 +43,18 @@ pub struct PlaceOrderParams {
     pub position_side: PositionSide,
     pub order_type: OrderType,
     pub stop_type: StopType,
+    pub time_in_force: TimeInForce,
+    pub expires_at: i64,
+    pub take_profit_price: u128,
+    pub stop_loss_price: u128,
+    pub trailing_callback: u128,
+    pub trailing_callback_type: TrailingCallbackType,
+    pub reduce_only: bool,
+    pub twap_slice_count: u16,
+    pub twap_interval: i64,
 }
 
-fn validate_place_order(
+pub fn validate_place_order(
     order: &PlaceOrderParams,
     token: &Pubkey,
     market: &Market,
@@ -42,7 +67,13 @@ fn validate_place_order(
         _ => {
             if order.position_side.eq(&PositionSide::DECREASE) && order.size == 0u128 {
                 Ok(false)
-            } else if order.order_side.eq(&OrderSide::NONE) {
+            } else if order.order_side.eq(&OrderSide::NONE)
+                || !validate_time_in_force(order, calculator::current_time())
+                || !validate_bracket_prices(order, token_price)
+                || !validate_trailing_stop(order)
+                || !validate_twap(order)
+                || (order.reduce_only && !order.position_side.eq(&PositionSide::DECREASE))
+            {
                 Ok(false)
             } else if order.order_type.eq(&OrderType::LIMIT)
                 && order.position_side.eq(&PositionSide::DECREASE)
@@ -85,3 +116,447 @@ fn validate_place_order(
         },
     }
 }
+
+fn validate_time_in_force(order: &PlaceOrderParams, now: i64) -> bool {
+    match order.time_in_force {
+        TimeInForce::GTC => order.expires_at == 0,
+        TimeInForce::GTT => !order.order_type.eq(&OrderType::MARKET) && order.expires_at > now,
+        //IOC and FOK never rest, they need a price bound to fail on
+        TimeInForce::IOC | TimeInForce::FOK => {
+            order.order_type.eq(&OrderType::MARKET)
+                && order.expires_at == 0
+                && order.acceptable_price > 0u128
+        },
+    }
+}
+
+fn validate_bracket_prices(order: &PlaceOrderParams, token_price: u128) -> bool {
+    if order.take_profit_price == 0u128 && order.stop_loss_price == 0u128 {
+        return true;
+    }
+    if !order.position_side.eq(&PositionSide::INCREASE) {
+        return false;
+    }
+    //compare against the price the increase order is expected to fill at
+    let open_price =
+        if order.order_type.eq(&OrderType::MARKET) { token_price } else { order.trigger_price };
+    let is_long = order.order_side.eq(&OrderSide::LONG);
+    let take_profit_valid = order.take_profit_price == 0u128
+        || if is_long {
+            order.take_profit_price > open_price
+        } else {
+            order.take_profit_price < open_price
+        };
+    let stop_loss_valid = order.stop_loss_price == 0u128
+        || if is_long {
+            order.stop_loss_price < open_price
+        } else {
+            order.stop_loss_price > open_price
+        };
+    take_profit_valid && stop_loss_valid
+}
+
+fn validate_trailing_stop(order: &PlaceOrderParams) -> bool {
+    if !order.order_type.eq(&OrderType::TRAILING_STOP) {
+        return order.trailing_callback == 0u128;
+    }
+    order.position_side.eq(&PositionSide::DECREASE)
+        && order.trailing_callback > 0u128
+        && (order.trailing_callback_type.eq(&TrailingCallbackType::Price)
+            || order.trailing_callback < BPS_PRECISION)
+}
+
+fn validate_twap(order: &PlaceOrderParams) -> bool {
+    if !order.order_type.eq(&OrderType::TWAP) {
+        return order.twap_slice_count == 0 && order.twap_interval == 0;
+    }
+    //every slice needs a price bound and something to fill, brackets can not follow the slices
+    let sliced_amount = match order.position_side {
+        PositionSide::INCREASE => order.order_margin,
+        _ => order.size,
+    };
+    order.twap_slice_count > 1
+        && order.twap_interval > 0
+        && order.acceptable_price > 0u128
+        && sliced_amount >= order.twap_slice_count.into()
+        && order.take_profit_price == 0u128
+        && order.stop_loss_price == 0u128
+}
+
+/// Builds the order placed by `params`. It takes the next order id of the user, an order with
+/// take-profit / stop-loss prices also reserves the two following ids for its bracket orders, and
+/// a trailing stop starts tracking from the trigger mark price at placement.
+fn build_user_order(
+    user: &mut User,
+    params: &PlaceOrderParams,
+    market: &Market,
+    index_price_data: &OraclePriceData,
+    margin_mint_key: &Pubkey,
+    user_token_account: Pubkey,
+    state: &State,
+) -> BumpResult<UserOrder> {
+    let (trailing_extreme_price, trailing_extreme_updated_at) =
+        if params.order_type.eq(&OrderType::TRAILING_STOP) {
+            (
+                market.get_mark_price(index_price_data.price, MarkPriceUsage::Trigger)?,
+                index_price_data.publish_time,
+            )
+        } else {
+            (0u128, 0i64)
+        };
+    let order_id = get_then_update_id!(user, next_order_id);
+    if params.take_profit_price > 0u128 || params.stop_loss_price > 0u128 {
+        user.next_order_id = order_id.safe_add(3)?;
+    }
+    Ok(UserOrder {
+        authority: user.authority,
+        order_id,
+        symbol: params.symbol,
+        order_side: params.order_side,
+        position_side: params.position_side,
+        order_type: params.order_type,
+        stop_type: params.stop_type,
+        is_portfolio_margin: params.is_portfolio_margin,
+        margin_mint_key: *margin_mint_key,
+        order_margin: params.order_margin,
+        leverage: params.leverage,
+        order_size: params.size,
+        trigger_price: params.trigger_price,
+        acceptable_price: params.acceptable_price,
+        take_profit_price: params.take_profit_price,
+        stop_loss_price: params.stop_loss_price,
+        trailing_callback: params.trailing_callback,
+        trailing_callback_type: params.trailing_callback_type,
+        reduce_only: params.reduce_only,
+        twap_slice_count: params.twap_slice_count,
+        twap_interval: params.twap_interval,
+        trailing_extreme_price,
+        trailing_extreme_updated_at,
+        created_at: calculator::current_time(),
+        expires_at: params.expires_at,
+        time_in_force: params.time_in_force,
+        execution_fee: state.execution_fee,
+        status: OrderStatus::USING,
+        user_token_account,
+        ..Default::default()
+    })
+}
+
+/// Stores an order in its own account with its escrowed execution fee and counts it as open.
+fn store_order<'info>(
+    user: &mut User,
+    user_order: &UserOrder,
+    order_account: &AccountLoader<'info, Order>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    escrow_execution_fee(
+        payer,
+        &order_account.to_account_info(),
+        system_program,
+        user_order.execution_fee,
+    )?;
+    let mut order = order_account.load_init()?;
+    order.user_key = user.key;
+    order.order = *user_order;
+    user.add_open_order()?;
+    emit!(AddOrDeleteUserOrderEvent { user_key: user.key, order: *user_order, is_add: true });
+    Ok(())
+}
+
+/// Stores the take-profit / stop-loss orders attached to an increase order. They stay pending
+/// until the increase order is executed, market orders pass the filled size to start them active.
+fn init_bracket_orders<'info>(
+    user: &mut User,
+    parent: &UserOrder,
+    take_profit_order: &Option<AccountLoader<'info, Order>>,
+    stop_loss_order: &Option<AccountLoader<'info, Order>>,
+    filled_size: Option<u128>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    for (stop_type, trigger_price, order_account) in [
+        (StopType::TakeProfit, parent.take_profit_price, take_profit_order),
+        (StopType::StopLoss, parent.stop_loss_price, stop_loss_order),
+    ] {
+        if trigger_price == 0u128 {
+            validate!(order_account.is_none(), BumpErrorCode::InvalidParam)?;
+            continue;
+        }
+        let order_account = order_account.as_ref().ok_or(BumpErrorCode::InvalidParam)?;
+        let bracket_order = UserOrder {
+            authority: parent.authority,
+            order_id: parent.get_bracket_order_id(stop_type)?,
+            parent_order_id: parent.order_id,
+            symbol: parent.symbol,
+            order_side: if parent.order_side.eq(&OrderSide::LONG) {
+                OrderSide::SHORT
+            } else {
+                OrderSide::LONG
+            },
+            position_side: PositionSide::DECREASE,
+            order_type: OrderType::STOP,
+            stop_type,
+            is_portfolio_margin: parent.is_portfolio_margin,
+            margin_mint_key: parent.margin_mint_key,
+            leverage: parent.leverage,
+            order_size: filled_size.unwrap_or(0u128),
+            trigger_price,
+            take_profit_price: parent.take_profit_price,
+            stop_loss_price: parent.stop_loss_price,
+            created_at: parent.created_at,
+            status: if filled_size.is_some() { OrderStatus::USING } else { OrderStatus::PENDING },
+            user_token_account: parent.user_token_account,
+            execution_fee: parent.execution_fee,
+            is_bracket: true,
+            reduce_only: true,
+            ..Default::default()
+        };
+        store_order(user, &bracket_order, order_account, payer, system_program)?;
+    }
+    Ok(())
+}
+
+/// Escrows the keeper execution fee in a stored order account. The executing keeper is paid out
+/// of it on fill, on cancel it is refunded to the user with the rent when the account is closed.
+fn escrow_execution_fee<'info>(
+    payer: &AccountInfo<'info>,
+    order_account: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    execution_fee: u64,
+) -> Result<()> {
+    if execution_fee == 0 {
+        return Ok(());
+    }
+    invoke(
+        &transfer(payer.key, order_account.key, execution_fee),
+        &[payer.clone(), order_account.clone(), system_program.clone()],
+    )
+    .map_err(|_e| BumpErrorCode::TransferFailed)?;
+    Ok(())
+}
+
+/// Creates the order account seeded by `[b"order", user, order_id]` out of the remaining
+/// accounts, for instructions placing a variable number of orders. Anchor does not run `exit` on
+/// it, the caller does so to write the discriminator once the order is initialized.
+pub fn create_order_account<'info>(
+    user_key: &Pubkey,
+    order_id: u64,
+    remaining_accounts: &'info [AccountInfo<'info>],
+    payer: &AccountInfo<'info>,
+    system_program: &Program<'info, System>,
+    program_id: &Pubkey,
+) -> Result<AccountLoader<'info, Order>> {
+    let order_id_bytes = order_id.to_le_bytes();
+    let (order_key, bump) = Pubkey::find_program_address(
+        &[b"order", user_key.as_ref(), order_id_bytes.as_ref()],
+        program_id,
+    );
+    let order_info = remaining_accounts
+        .iter()
+        .find(|account_info| account_info.key.eq(&order_key))
+        .ok_or(BumpErrorCode::CouldNotFindUserOrder)?;
+    let signer_seeds: &[&[&[u8]]] =
+        &[&[b"order", user_key.as_ref(), order_id_bytes.as_ref(), &[bump]]];
+    let rent = Rent::get()?.minimum_balance(Order::SIZE);
+    let current_lamports = order_info.lamports();
+    if current_lamports == 0 {
+        system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                system_program::CreateAccount { from: payer.clone(), to: order_info.clone() },
+                signer_seeds,
+            ),
+            rent,
+            Order::SIZE as u64,
+            program_id,
+        )?;
+    } else {
+        //the address may be funded in advance, which makes create_account fail
+        let required_lamports = rent.max(1).saturating_sub(current_lamports);
+        if required_lamports > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    system_program.to_account_info(),
+                    system_program::Transfer { from: payer.clone(), to: order_info.clone() },
+                ),
+                required_lamports,
+            )?;
+        }
+        system_program::allocate(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                system_program::Allocate { account_to_allocate: order_info.clone() },
+                signer_seeds,
+            ),
+            Order::SIZE as u64,
+        )?;
+        system_program::assign(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                system_program::Assign { account_to_assign: order_info.clone() },
+                signer_seeds,
+            ),
+            program_id,
+        )?;
+    }
+    AccountLoader::try_from_unchecked(program_id, order_info)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::instructions::place::{
+        validate_bracket_prices, validate_time_in_force, validate_trailing_stop, validate_twap,
+        PlaceOrderParams,
+    };
+    use crate::math::constants::BPS_PRECISION;
+    use crate::state::infrastructure::user_order::{
+        OrderSide, OrderType, PositionSide, StopType, TimeInForce, TrailingCallbackType,
+    };
+
+    fn place_order_params(order_type: OrderType, time_in_force: TimeInForce) -> PlaceOrderParams {
+        PlaceOrderParams {
+            symbol: [0u8; 32],
+            size: 1_000,
+            order_margin: 100,
+            leverage: 10,
+            trigger_price: 0,
+            acceptable_price: 0,
+            place_time: 0,
+            is_portfolio_margin: false,
+            is_native_token: false,
+            order_side: OrderSide::LONG,
+            position_side: PositionSide::INCREASE,
+            order_type,
+            stop_type: StopType::NONE,
+            time_in_force,
+            expires_at: 0,
+            take_profit_price: 0,
+            stop_loss_price: 0,
+            trailing_callback: 0,
+            trailing_callback_type: TrailingCallbackType::Bps,
+            reduce_only: false,
+            twap_slice_count: 0,
+            twap_interval: 0,
+        }
+    }
+
+    #[test]
+    fn test_validate_time_in_force() {
+        let now = 1_000i64;
+
+        let mut order = place_order_params(OrderType::LIMIT, TimeInForce::GTC);
+        assert!(validate_time_in_force(&order, now));
+        order.expires_at = now + 1;
+        assert!(!validate_time_in_force(&order, now));
+
+        let mut order = place_order_params(OrderType::LIMIT, TimeInForce::GTT);
+        order.expires_at = now + 1;
+        assert!(validate_time_in_force(&order, now));
+        order.expires_at = now;
+        assert!(!validate_time_in_force(&order, now));
+        let mut order = place_order_params(OrderType::MARKET, TimeInForce::GTT);
+        order.expires_at = now + 1;
+        assert!(!validate_time_in_force(&order, now));
+
+        for time_in_force in [TimeInForce::IOC, TimeInForce::FOK] {
+            let mut order = place_order_params(OrderType::MARKET, time_in_force);
+            assert!(!validate_time_in_force(&order, now));
+            order.acceptable_price = 100;
+            assert!(validate_time_in_force(&order, now));
+            order.expires_at = now + 1;
+            assert!(!validate_time_in_force(&order, now));
+            let mut order = place_order_params(OrderType::LIMIT, time_in_force);
+            order.acceptable_price = 100;
+            assert!(!validate_time_in_force(&order, now));
+        }
+    }
+
+    #[test]
+    fn test_validate_bracket_prices() {
+        let token_price = 100u128;
+
+        let mut order = place_order_params(OrderType::MARKET, TimeInForce::GTC);
+        assert!(validate_bracket_prices(&order, token_price));
+        order.take_profit_price = 110;
+        order.stop_loss_price = 90;
+        assert!(validate_bracket_prices(&order, token_price));
+        order.take_profit_price = 90;
+        assert!(!validate_bracket_prices(&order, token_price));
+        order.take_profit_price = 0;
+        order.stop_loss_price = 110;
+        assert!(!validate_bracket_prices(&order, token_price));
+
+        order.order_side = OrderSide::SHORT;
+        order.take_profit_price = 90;
+        order.stop_loss_price = 110;
+        assert!(validate_bracket_prices(&order, token_price));
+        order.stop_loss_price = 90;
+        assert!(!validate_bracket_prices(&order, token_price));
+
+        //a resting order is compared against its trigger price
+        let mut order = place_order_params(OrderType::LIMIT, TimeInForce::GTC);
+        order.trigger_price = 80;
+        order.take_profit_price = 90;
+        order.stop_loss_price = 70;
+        assert!(validate_bracket_prices(&order, token_price));
+
+        order.position_side = PositionSide::DECREASE;
+        assert!(!validate_bracket_prices(&order, token_price));
+    }
+
+    #[test]
+    fn test_validate_trailing_stop() {
+        let mut order = place_order_params(OrderType::LIMIT, TimeInForce::GTC);
+        assert!(validate_trailing_stop(&order));
+        order.trailing_callback = 100;
+        assert!(!validate_trailing_stop(&order));
+
+        let mut order = place_order_params(OrderType::TRAILING_STOP, TimeInForce::GTC);
+        order.position_side = PositionSide::DECREASE;
+        assert!(!validate_trailing_stop(&order));
+        order.trailing_callback = BPS_PRECISION - 1;
+        assert!(validate_trailing_stop(&order));
+        order.trailing_callback = BPS_PRECISION;
+        assert!(!validate_trailing_stop(&order));
+        order.trailing_callback_type = TrailingCallbackType::Price;
+        assert!(validate_trailing_stop(&order));
+
+        order.position_side = PositionSide::INCREASE;
+        assert!(!validate_trailing_stop(&order));
+    }
+
+    #[test]
+    fn test_validate_twap() {
+        let order = place_order_params(OrderType::LIMIT, TimeInForce::GTC);
+        assert!(validate_twap(&order));
+
+        let mut order = place_order_params(OrderType::TWAP, TimeInForce::GTC);
+        assert!(!validate_twap(&order));
+        order.twap_slice_count = 4;
+        order.twap_interval = 60;
+        order.acceptable_price = 100;
+        assert!(validate_twap(&order));
+
+        order.twap_slice_count = 1;
+        assert!(!validate_twap(&order));
+        order.twap_slice_count = 4;
+        order.twap_interval = 0;
+        assert!(!validate_twap(&order));
+        order.twap_interval = 60;
+        order.acceptable_price = 0;
+        assert!(!validate_twap(&order));
+        order.acceptable_price = 100;
+        order.take_profit_price = 110;
+        assert!(!validate_twap(&order));
+        order.take_profit_price = 0;
+
+        //every slice needs something to fill
+        order.order_margin = 3;
+        assert!(!validate_twap(&order));
+        order.position_side = PositionSide::DECREASE;
+        assert!(validate_twap(&order));
+        order.size = 3;
+        assert!(!validate_twap(&order));
+    }
+}
@@ -0,0 +1,190 @@
+use std::ops::{Deref, DerefMut};
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount};
+
+use crate::errors::BumpErrorCode;
+use crate::instructions::constraints::*;
+use crate::instructions::place::{
+    build_user_order, create_order_account, init_bracket_orders, store_order, validate_place_order,
+};
+use crate::instructions::PlaceOrderParams;
+use crate::processor::optional_accounts::{load_maps, AccountMaps};
+use crate::processor::position_processor::use_base_token;
+use crate::state::infrastructure::user_order::{OrderType, PositionSide, StopType};
+use crate::state::state::{PauseOperation, State};
+use crate::state::user::User;
+use crate::utils::token;
+use crate::validate;
+
+#[derive(Accounts)]
+pub struct PlaceOrders<'info> {
+    #[account(
+        seeds = [b"bump_state".as_ref()],
+        bump,
+    )]
+    pub state: Box<Account<'info, State>>,
+
+    #[account(
+        mut,
+        seeds = [b"user", authority.key().as_ref()],
+        bump,
+        constraint = can_sign_for_user(& user, & authority) ? && is_normal(& user) ?,
+    )]
+    pub user: AccountLoader<'info, User>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        token::authority = authority
+    )]
+    pub user_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(address = Token::id())]
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Places several resting orders at once, an invalid order fails the whole batch. The order
+/// accounts, seeded by the ids the orders take in turn, are passed writable in the remaining
+/// accounts together with the markets, pools, oracles and pool vaults.
+pub fn handle_place_orders<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, PlaceOrders<'c>>,
+    orders: Vec<PlaceOrderParams>,
+) -> Result<()> {
+    ctx.accounts.state.validate_operation_not_paused(PauseOperation::PlaceOrder)?;
+    validate!(!orders.is_empty(), BumpErrorCode::InvalidParam)?;
+    let remaining_accounts = ctx.remaining_accounts;
+    let AccountMaps { mut oracle_map, market_map, pool_map, vault_map, .. } =
+        load_maps(remaining_accounts)?;
+    let mut user = ctx.accounts.user.load_mut()?;
+    let payer = ctx.accounts.authority.to_account_info();
+    let system_program = ctx.accounts.system_program.to_account_info();
+    let mut order_accounts = Vec::new();
+    for order in orders {
+        //market orders execute right away, they are placed one at a time
+        validate!(!order.order_type.eq(&OrderType::MARKET), BumpErrorCode::InvalidParam)?;
+        let market = market_map.get_ref(&order.symbol)?;
+        let pool = match use_base_token(&order.position_side, &order.order_side)? {
+            true => pool_map.get_ref(&market.pool_key)?,
+            false => pool_map.get_ref(&market.stable_pool_key)?,
+        };
+        let margin_token = match use_base_token(&order.position_side, &order.order_side)? {
+            true => &market.pool_mint_key,
+            false => &market.stable_pool_mint_key,
+        };
+        let user_token_account = match order.is_portfolio_margin {
+            true => None,
+            false => {
+                let user_token_account = ctx
+                    .accounts
+                    .user_token_account
+                    .as_ref()
+                    .ok_or(BumpErrorCode::InvalidTokenAccount)?;
+                validate!(
+                    user_token_account.mint.eq(margin_token),
+                    BumpErrorCode::InvalidTokenAccount
+                )?;
+                Some(user_token_account)
+            },
+        };
+
+        let index_price_data = oracle_map
+            .get_price_data(&market.index_mint_oracle)
+            .map_err(|_e| BumpErrorCode::OracleNotFound)?;
+        let token_price = index_price_data.price;
+        validate!(
+            validate_place_order(
+                &order,
+                margin_token,
+                &market,
+                pool.deref(),
+                &ctx.accounts.state,
+                token_price
+            )?,
+            BumpErrorCode::InvalidParam
+        )?;
+        if !user.make_order_is_allowed(
+            order.symbol,
+            order.is_portfolio_margin,
+            use_base_token(&order.position_side, &order.order_side)?,
+            ctx.program_id,
+        )? {
+            return Err(BumpErrorCode::OnlyOneTypeOrderAllowed.into());
+        }
+
+        if order.position_side.eq(&PositionSide::INCREASE) {
+            match user_token_account {
+                //isolate order, transfer order_margin into pool
+                Some(user_token_account) => token::receive(
+                    &ctx.accounts.token_program,
+                    user_token_account,
+                    vault_map.get_account(&pool.pool_vault_key)?,
+                    &ctx.accounts.authority,
+                    order.order_margin,
+                )?,
+                //hold usd
+                None => user.deref_mut().add_order_hold_in_usd(order.order_margin)?,
+            }
+        }
+
+        let user_order = build_user_order(
+            &mut user,
+            &order,
+            &market,
+            index_price_data,
+            margin_token,
+            user_token_account
+                .map(|user_token_account| user_token_account.key())
+                .unwrap_or_default(),
+            &ctx.accounts.state,
+        )?;
+
+        let order_account = create_order_account(
+            &user.key,
+            user_order.order_id,
+            remaining_accounts,
+            &payer,
+            &ctx.accounts.system_program,
+            ctx.program_id,
+        )?;
+        store_order(&mut user, &user_order, &order_account, &payer, &system_program)?;
+        order_accounts.push(order_account);
+
+        let mut bracket_order_accounts = Vec::with_capacity(2);
+        for (stop_type, trigger_price) in [
+            (StopType::TakeProfit, order.take_profit_price),
+            (StopType::StopLoss, order.stop_loss_price),
+        ] {
+            bracket_order_accounts.push(match trigger_price > 0u128 {
+                true => Some(create_order_account(
+                    &user.key,
+                    user_order.get_bracket_order_id(stop_type)?,
+                    remaining_accounts,
+                    &payer,
+                    &ctx.accounts.system_program,
+                    ctx.program_id,
+                )?),
+                false => None,
+            });
+        }
+        init_bracket_orders(
+            &mut user,
+            &user_order,
+            &bracket_order_accounts[0],
+            &bracket_order_accounts[1],
+            None,
+            &payer,
+            &system_program,
+        )?;
+        order_accounts.extend(bracket_order_accounts.into_iter().flatten());
+    }
+
+    for order_account in order_accounts {
+        order_account.exit(ctx.program_id)?;
+    }
+    Ok(())
+}
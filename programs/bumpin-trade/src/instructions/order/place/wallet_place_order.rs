@@ -5,16 +5,22 @@ use anchor_spl::token::{Token, TokenAccount};
 
 use crate::errors::BumpErrorCode;
 use crate::instructions::constraints::*;
-use crate::instructions::place::validate_place_order;
-use crate::instructions::{calculator, PlaceOrderParams};
+use crate::instructions::place::{
+    build_user_order, init_bracket_orders, store_order, validate_place_order,
+};
+use crate::instructions::PlaceOrderParams;
+use crate::math::safe_math::SafeMath;
 use crate::processor::optional_accounts::{load_maps, AccountMaps};
 use crate::processor::position_processor;
 use crate::processor::position_processor::use_base_token;
-use crate::state::infrastructure::user_order::{OrderStatus, OrderType, PositionSide, UserOrder};
-use crate::state::state::State;
+use crate::state::infrastructure::user_order::{OrderType, PositionSide};
+use crate::state::order::Order;
+use crate::state::order_map::OrderMap;
+use crate::state::state::{PauseOperation, State};
 use crate::state::user::User;
+use crate::traits::Size;
 use crate::utils::token;
-use crate::{get_then_update_id, validate};
+use crate::validate;
 
 #[derive(Accounts)]
 pub struct WalletPlaceOrder<'info> {
@@ -32,8 +38,36 @@ pub struct WalletPlaceOrder<'info> {
     )]
     pub user: AccountLoader<'info, User>,
 
+    #[account(mut)]
     pub authority: Signer<'info>,
 
+    #[account(
+        init,
+        payer = authority,
+        space = Order::SIZE,
+        seeds = [b"order", user.key().as_ref(), user.load()?.next_order_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub order: Option<AccountLoader<'info, Order>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = Order::SIZE,
+        seeds = [b"order", user.key().as_ref(), user.load()?.next_order_id.safe_add(1)?.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub take_profit_order: Option<AccountLoader<'info, Order>>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = Order::SIZE,
+        seeds = [b"order", user.key().as_ref(), user.load()?.next_order_id.safe_add(2)?.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub stop_loss_order: Option<AccountLoader<'info, Order>>,
+
     #[account(
         mut,
         token::authority = authority
@@ -48,12 +82,15 @@ pub struct WalletPlaceOrder<'info> {
 
     #[account(address = Token::id())]
     pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
 }
 
 pub fn handle_place_wallet_order<'a, 'b, 'c: 'info, 'info>(
     ctx: Context<'a, 'b, 'c, 'info, WalletPlaceOrder<'c>>,
     order: PlaceOrderParams,
 ) -> Result<()> {
+    ctx.accounts.state.validate_operation_not_paused(PauseOperation::PlaceOrder)?;
     msg!("============handle_place_order, order:{:?}", order);
     let remaining_accounts = ctx.remaining_accounts;
     let AccountMaps { trade_token_map, mut oracle_map, market_map, pool_map, vault_map } =
@@ -73,10 +110,10 @@ pub fn handle_place_wallet_order<'a, 'b, 'c: 'info, 'info>(
         BumpErrorCode::InvalidTokenAccount
     )?;
 
-    let token_price = oracle_map
+    let index_price_data = oracle_map
         .get_price_data(&market.index_mint_oracle)
-        .map_err(|_e| BumpErrorCode::OracleNotFound)?
-        .price;
+        .map_err(|_e| BumpErrorCode::OracleNotFound)?;
+    let token_price = index_price_data.price;
     validate!(
         validate_place_order(
             &order,
@@ -114,35 +151,33 @@ pub fn handle_place_wallet_order<'a, 'b, 'c: 'info, 'info>(
         return Err(BumpErrorCode::OnlyOneTypeOrderAllowed.into());
     }
 
-    let order_id = get_then_update_id!(user, next_order_id);
-    let user_order = UserOrder {
-        authority: user.authority,
-        order_id,
-        symbol: order.symbol,
-        order_side: order.order_side,
-        position_side: order.position_side,
-        order_type: order.order_type,
-        stop_type: order.stop_type,
-        is_portfolio_margin: order.is_portfolio_margin,
-        margin_mint_key: margin_token.key(),
-        order_margin: order.order_margin,
-        leverage: order.leverage,
-        order_size: order.size,
-        trigger_price: order.trigger_price,
-        acceptable_price: order.acceptable_price,
-        created_at: calculator::current_time(),
-        status: OrderStatus::USING,
-        user_token_account: ctx.accounts.user_token_account.key(),
-        ..Default::default()
-    };
+    let user_order = build_user_order(
+        &mut user,
+        &order,
+        &market,
+        index_price_data,
+        margin_token,
+        ctx.accounts.user_token_account.key(),
+        &ctx.accounts.state,
+    )?;
     if order.order_type.eq(&OrderType::MARKET) {
         //execute order immediately
+        validate!(ctx.accounts.order.is_none(), BumpErrorCode::InvalidParam)?;
+        let mut order_map = OrderMap::load(remaining_accounts, &user)?;
+        let position_key = user.get_position_key(
+            user_order.symbol,
+            user_order.is_portfolio_margin,
+            user_order.is_long_position(),
+            ctx.program_id,
+        )?;
+        let pre_position_size = user.get_position_size(&position_key);
+        ctx.accounts.state.validate_operation_not_paused(PauseOperation::ExecuteOrder)?;
         drop(market);
         drop(pool);
         let state_account = &ctx.accounts.state;
         let bump_signer_account_info = &ctx.accounts.bump_signer;
         let token_program = &ctx.accounts.token_program;
-        position_processor::handle_execute_order(
+        let executed = position_processor::handle_execute_order(
             user.deref_mut(),
             &market_map,
             &pool_map,
@@ -154,12 +189,39 @@ pub fn handle_place_wallet_order<'a, 'b, 'c: 'info, 'info>(
             ctx.program_id,
             &trade_token_map,
             &mut oracle_map,
+            &mut order_map,
             &user_order,
         )?;
+        //nothing stays open to retry a market order against a later price, it is rejected
+        validate!(executed, BumpErrorCode::MarketPriceDeviationTooLarge)?;
+        let filled_size = user.get_position_size(&position_key).safe_sub(pre_position_size)?;
+        init_bracket_orders(
+            &mut user,
+            &user_order,
+            &ctx.accounts.take_profit_order,
+            &ctx.accounts.stop_loss_order,
+            Some(filled_size),
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
     } else {
-        //store order, wait to execute
-        let next_index = user.next_usable_order_index()?;
-        user.add_order(&user_order, next_index)?;
+        //store order in its own account, wait to execute
+        store_order(
+            &mut user,
+            &user_order,
+            ctx.accounts.order.as_ref().ok_or(BumpErrorCode::InvalidParam)?,
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+        init_bracket_orders(
+            &mut user,
+            &user_order,
+            &ctx.accounts.take_profit_order,
+            &ctx.accounts.stop_loss_order,
+            None,
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
     }
     Ok(())
 }
@@ -9,7 +9,7 @@ use crate::processor::optional_accounts::load_maps;
 use crate::processor::{pool_processor, stake_processor};
 use crate::state::bump_events::StakeOrUnStakeEvent;
 use crate::state::pool::Pool;
-use crate::state::state::State;
+use crate::state::state::{PauseOperation, State};
 use crate::state::User;
 use crate::utils;
 
@@ -72,6 +72,7 @@ pub fn handle_portfolio_stake<'a, 'b, 'c: 'info, 'info>(
     _trade_token_index: u16,
     request_token_amount: u128,
 ) -> Result<()> {
+    ctx.accounts.state.validate_operation_not_paused(PauseOperation::Stake)?;
     let pool = &mut ctx.accounts.pool.load_mut()?;
     let user = &mut ctx.accounts.user.load_mut()?;
     let remaining_accounts = ctx.remaining_accounts;
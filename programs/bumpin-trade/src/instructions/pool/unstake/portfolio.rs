@@ -10,7 +10,7 @@ use crate::processor::optional_accounts::load_maps;
 use crate::processor::{fee_processor, pool_processor, user_processor};
 use crate::state::bump_events::StakeOrUnStakeEvent;
 use crate::state::pool::Pool;
-use crate::state::state::State;
+use crate::state::state::{PauseOperation, State};
 use crate::state::trade_token::TradeToken;
 use crate::state::{User, UserTokenUpdateReason};
 use crate::{utils, validate};
@@ -78,6 +78,7 @@ pub fn handle_portfolio_un_stake<'a, 'b, 'c: 'info, 'info>(
     ctx: Context<'a, 'b, 'c, 'info, PortfolioUnStake>,
     param: UnStakeParams,
 ) -> anchor_lang::Result<()> {
+    ctx.accounts.state.validate_operation_not_paused(PauseOperation::UnStake)?;
     let pool = &mut ctx.accounts.pool.load_mut()?;
     let user = &mut ctx.accounts.user.load_mut()?;
 
@@ -9,7 +9,7 @@ use crate::processor::optional_accounts::load_maps;
 use crate::processor::{fee_processor, pool_processor, user_processor};
 use crate::state::bump_events::StakeOrUnStakeEvent;
 use crate::state::pool::Pool;
-use crate::state::state::State;
+use crate::state::state::{PauseOperation, State};
 use crate::state::trade_token::TradeToken;
 use crate::state::User;
 use crate::{utils, validate};
@@ -84,6 +84,7 @@ pub fn handle_wallet_un_stake<'a, 'b, 'c: 'info, 'info>(
     ctx: Context<'a, 'b, 'c, 'info, WalletUnStake>,
     param: UnStakeParams,
 ) -> anchor_lang::Result<()> {
+    ctx.accounts.state.validate_operation_not_paused(PauseOperation::UnStake)?;
     let pool = &mut ctx.accounts.pool.load_mut()?;
     let user = &mut ctx.accounts.user.load_mut()?;
 
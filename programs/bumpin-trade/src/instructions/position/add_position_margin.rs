@@ -6,8 +6,9 @@ use crate::instructions::constraints::*;
 use crate::processor::optional_accounts::{load_maps, AccountMaps};
 use crate::processor::position_processor;
 use crate::state::market::Market;
+use crate::state::order_map::OrderMap;
 use crate::state::pool::Pool;
-use crate::state::state::State;
+use crate::state::state::{PauseOperation, State};
 use crate::state::trade_token::TradeToken;
 use crate::state::user::User;
 use crate::utils::token;
@@ -102,6 +103,11 @@ pub fn handle_add_position_margin<'a, 'b, 'c: 'info, 'info>(
     ctx: Context<'a, 'b, 'c, 'info, AddPositionMargin>,
     params: UpdatePositionMarginParams,
 ) -> Result<()> {
+    ctx.accounts.state.validate_operation_not_paused(if params.is_add {
+        PauseOperation::Deposit
+    } else {
+        PauseOperation::Withdraw
+    })?;
     validate!(params.update_margin_amount > 0u128, BumpErrorCode::AmountNotEnough)?;
     let market = ctx.accounts.market.load_mut()?;
     let remaining_accounts = ctx.remaining_accounts;
@@ -167,7 +173,10 @@ pub fn handle_add_position_margin<'a, 'b, 'c: 'info, 'info>(
         )?;
     }
     let position = *position;
-    user.update_all_orders_leverage(
+    //every open order of the position follows the new leverage
+    let order_map = OrderMap::load(remaining_accounts, &user)?;
+    order_map.validate_all_orders_loaded(&user, None)?;
+    order_map.update_all_orders_leverage(
         position.leverage,
         position.symbol,
         &position.margin_mint_key,
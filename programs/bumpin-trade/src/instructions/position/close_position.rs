@@ -0,0 +1,146 @@
+use std::ops::DerefMut;
+
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount};
+
+use crate::errors::BumpErrorCode;
+use crate::instructions::constraints::*;
+use crate::math::constants::BPS_PRECISION;
+use crate::math::safe_math::SafeMath;
+use crate::processor::optional_accounts::{load_maps, AccountMaps};
+use crate::processor::position_processor;
+use crate::state::infrastructure::user_order::{
+    OrderSide, OrderStatus, OrderType, PositionSide, UserOrder,
+};
+use crate::state::order_map::OrderMap;
+use crate::state::state::{PauseOperation, State};
+use crate::state::user::User;
+use crate::validate;
+
+#[derive(Accounts)]
+pub struct ClosePosition<'info> {
+    #[account(
+        seeds = [b"bump_state".as_ref()],
+        bump,
+    )]
+    pub state: Box<Account<'info, State>>,
+
+    #[account(
+        mut,
+        seeds = [b"user", authority.key().as_ref()],
+        bump,
+        constraint = can_sign_for_user(& user, & authority) ? && is_normal(& user) ?,
+    )]
+    pub user: AccountLoader<'info, User>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        token::authority = authority
+    )]
+    pub user_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        constraint = state.bump_signer.eq(& bump_signer.key())
+    )]
+    /// CHECK: ?
+    pub bump_signer: AccountInfo<'info>,
+
+    #[account(address = Token::id())]
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ClosePositionSize {
+    Size(u128),
+    /// share of the position size in bps
+    Rate(u32),
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Eq, PartialEq)]
+pub struct ClosePositionParams {
+    pub position_key: Pubkey,
+    pub close_size: ClosePositionSize,
+    pub acceptable_price: u128,
+}
+
+/// Closes a position, in full or in part, at the current oracle price without a keeper round
+/// trip. The close runs as a market decrease order bounded by `acceptable_price`, so it goes
+/// through the same price, circuit breaker and fee updates as an executed order.
+pub fn handle_close_position<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, ClosePosition<'c>>,
+    params: ClosePositionParams,
+) -> Result<()> {
+    ctx.accounts.state.validate_operation_not_paused(PauseOperation::ExecuteOrder)?;
+    validate!(params.acceptable_price > 0u128, BumpErrorCode::InvalidParam)?;
+    let remaining_accounts = ctx.remaining_accounts;
+    let AccountMaps { trade_token_map, mut oracle_map, market_map, pool_map, vault_map } =
+        load_maps(remaining_accounts)?;
+    let mut user = ctx.accounts.user.load_mut()?;
+    let mut order_map = OrderMap::load(remaining_accounts, &user)?;
+    let position = *user.get_user_position_ref(&params.position_key)?;
+    validate!(position.position_size > 0u128, BumpErrorCode::CouldNotFindUserPosition)?;
+
+    let close_size = match params.close_size {
+        ClosePositionSize::Size(size) => size.min(position.position_size),
+        ClosePositionSize::Rate(rate) => {
+            validate!(u128::from(rate) <= BPS_PRECISION, BumpErrorCode::InvalidParam)?;
+            position.position_size.safe_mul(rate.into())?.safe_div(BPS_PRECISION)?
+        },
+    };
+    validate!(close_size > 0u128, BumpErrorCode::InvalidParam)?;
+
+    let user_token_account = match position.is_portfolio_margin {
+        true => None,
+        false => {
+            //an isolate position settles into the wallet it was opened from
+            let user_token_account = ctx
+                .accounts
+                .user_token_account
+                .as_ref()
+                .ok_or(BumpErrorCode::InvalidTokenAccount)?;
+            validate!(
+                user_token_account.key().eq(&position.user_token_account)
+                    && user_token_account.mint.eq(&position.margin_mint_key),
+                BumpErrorCode::InvalidTokenAccount
+            )?;
+            Some(user_token_account)
+        },
+    };
+
+    let close_order = UserOrder {
+        authority: user.authority,
+        symbol: position.symbol,
+        order_side: if position.is_long { OrderSide::SHORT } else { OrderSide::LONG },
+        position_side: PositionSide::DECREASE,
+        order_type: OrderType::MARKET,
+        is_portfolio_margin: position.is_portfolio_margin,
+        margin_mint_key: position.margin_mint_key,
+        leverage: position.leverage,
+        order_size: close_size,
+        acceptable_price: params.acceptable_price,
+        status: OrderStatus::USING,
+        user_token_account: position.user_token_account,
+        reduce_only: true,
+        ..Default::default()
+    };
+    let executed = position_processor::handle_execute_order(
+        user.deref_mut(),
+        &market_map,
+        &pool_map,
+        &ctx.accounts.state,
+        user_token_account,
+        &vault_map,
+        &ctx.accounts.bump_signer,
+        &ctx.accounts.token_program,
+        ctx.program_id,
+        &trade_token_map,
+        &mut oracle_map,
+        &mut order_map,
+        &close_order,
+    )?;
+    //nothing stays open to retry a market order against a later price, it is rejected
+    validate!(executed, BumpErrorCode::MarketPriceDeviationTooLarge)?;
+    Ok(())
+}
@@ -1,5 +1,7 @@
 pub mod add_position_margin;
+pub mod close_position;
 pub mod update_position_leverage;
 
 pub use add_position_margin::*;
+pub use close_position::*;
 pub use update_position_leverage::*;
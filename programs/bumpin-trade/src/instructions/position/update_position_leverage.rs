@@ -7,9 +7,8 @@ use crate::processor::optional_accounts::{load_maps, AccountMaps};
 use crate::processor::position_processor;
 use crate::state::market::Market;
 use crate::state::pool::Pool;
-use crate::state::state::State;
+use crate::state::state::{PauseOperation, State};
 use crate::state::user::User;
-use crate::utils::pda;
 use crate::validate;
 
 #[derive(Accounts)]
@@ -93,6 +92,7 @@ pub fn handle_update_position_leverage<'a, 'b, 'c: 'info, 'info>(
     ctx: Context<'a, 'b, 'c, 'info, UpdatePositionLeverage>,
     params: UpdatePositionLeverageParams,
 ) -> Result<()> {
+    ctx.accounts.state.validate_operation_not_paused(PauseOperation::ExecuteOrder)?;
     let user = &mut ctx.accounts.user.load_mut()?;
     let pool = &mut ctx.accounts.pool.load_mut()?;
     let stable_pool = &mut ctx.accounts.stable_pool.load_mut()?;
@@ -107,10 +107,10 @@ pub fn handle_update_position_leverage<'a, 'b, 'c: 'info, 'info>(
         BumpErrorCode::LeverageIsNotAllowed
     )?;
 
-    let position_key = pda::generate_position_key(
-        &user.key,
+    let position_key = user.get_position_key(
         params.symbol,
         params.is_portfolio_margin,
+        params.is_long,
         ctx.program_id,
     )?;
     {
@@ -5,7 +5,7 @@ use crate::processor::{pool_processor, stake_processor};
 use crate::state::bump_events::StakeOrUnStakeEvent;
 use crate::state::infrastructure::user_stake::UserStakeStatus;
 use crate::state::pool::Pool;
-use crate::state::state::State;
+use crate::state::state::{PauseOperation, State};
 use crate::state::user::User;
 use crate::utils::token;
 use crate::validate;
@@ -67,6 +67,7 @@ pub fn handle_auto_compound<'a, 'b, 'c: 'info, 'info>(
     ctx: Context<'a, 'b, 'c, 'info, AutoCompoundRewards<'c>>,
     _pool_index: u16,
 ) -> Result<()> {
+    ctx.accounts.state.validate_operation_not_paused(PauseOperation::Stake)?;
     let mut user = ctx.accounts.user.load_mut()?;
     let mut pool = ctx.accounts.pool.load_mut()?;
     let user_stake = *user.get_user_stake_ref(&pool.key)?;
@@ -8,7 +8,7 @@ use crate::processor::user_processor;
 use crate::state::infrastructure::user_stake::UserStakeStatus;
 use crate::state::pool::Pool;
 use crate::state::rewards::Rewards;
-use crate::state::state::State;
+use crate::state::state::{PauseOperation, State};
 use crate::state::user::User;
 use crate::{utils, validate};
 
@@ -79,6 +79,7 @@ pub struct ClaimRewards<'info> {
 pub fn handle_claim_rewards<'a, 'b, 'c: 'info, 'info>(
     ctx: Context<'a, 'b, 'c, 'info, ClaimRewards<'c>>,
 ) -> Result<()> {
+    ctx.accounts.state.validate_operation_not_paused(PauseOperation::Withdraw)?;
     let mut user = ctx.accounts.user.load_mut()?;
     let mut pool = ctx.accounts.pool.load_mut()?;
     let mut reward = ctx.accounts.rewards.load_mut()?;
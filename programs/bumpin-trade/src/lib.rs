@@ -1,6 +1,7 @@
 use anchor_lang::prelude::*;
 
 use instructions::*;
+use state::oracle::OracleSource;
 
 pub mod errors;
 pub mod ids;
@@ -37,6 +38,67 @@ pub mod bumpin_trade {
         handle_modify_state(ctx, param)
     }
 
+    #[track_caller]
+    pub fn transfer_admin<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, TransferAdmin>,
+        new_admin: [u8; 32],
+    ) -> Result<()> {
+        handle_transfer_admin(ctx, new_admin)
+    }
+
+    #[track_caller]
+    pub fn migrate_state<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, MigrateState>,
+    ) -> Result<()> {
+        handle_migrate_state(ctx)
+    }
+
+    #[track_caller]
+    pub fn migrate_market<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, MigrateMarket>,
+        market_index: u16,
+    ) -> Result<()> {
+        handle_migrate_market(ctx, market_index)
+    }
+
+    #[track_caller]
+    pub fn migrate_trade_token<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, MigrateTradeToken>,
+        trade_token_index: u16,
+    ) -> Result<()> {
+        handle_migrate_trade_token(ctx, trade_token_index)
+    }
+
+    #[track_caller]
+    pub fn accept_admin<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, AcceptAdmin>,
+    ) -> Result<()> {
+        handle_accept_admin(ctx)
+    }
+
+    #[track_caller]
+    pub fn update_keeper<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, UpdateKeeper>,
+        keeper_key: [u8; 32],
+    ) -> Result<()> {
+        handle_update_keeper(ctx, keeper_key)
+    }
+
+    #[track_caller]
+    pub fn initialize_keeper_registry<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, InitializeKeeperRegistry>,
+    ) -> Result<()> {
+        handle_initialize_keeper_registry(ctx)
+    }
+
+    #[track_caller]
+    pub fn set_keeper<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, SetKeeper>,
+        params: SetKeeperParams,
+    ) -> Result<()> {
+        handle_set_keeper(ctx, params)
+    }
+
     #[track_caller]
     pub fn initialize_pool<'a, 'b, 'c: 'info, 'info>(
         ctx: Context<'a, 'b, 'c, 'info, InitializePool>,
@@ -52,14 +114,47 @@ pub mod bumpin_trade {
         handle_initialize_user(ctx)
     }
 
+    #[track_caller]
+    pub fn set_hedge_mode<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, SetHedgeMode>,
+        hedge_mode: bool,
+    ) -> Result<()> {
+        handle_set_hedge_mode(ctx, hedge_mode)
+    }
+
     #[track_caller]
     pub fn initialize_trade_token<'a, 'b, 'c: 'info, 'info>(
         ctx: Context<'a, 'b, 'c, 'info, InitializeTradeToken>,
         discount: u32,
         mint_name: [u8; 32],
         liquidation_factor: u32,
+        oracle_source: OracleSource,
+        oracle_feed_id: [u8; 32],
     ) -> Result<()> {
-        handle_initialize_trade_token(ctx, discount, mint_name, liquidation_factor)
+        handle_initialize_trade_token(
+            ctx,
+            discount,
+            mint_name,
+            liquidation_factor,
+            oracle_source,
+            oracle_feed_id,
+        )
+    }
+
+    #[track_caller]
+    pub fn modify_trade_token<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, ModifyTradeToken>,
+        params: ModifyTradeTokenParams,
+    ) -> Result<()> {
+        handle_modify_trade_token(ctx, params)
+    }
+
+    #[track_caller]
+    pub fn set_trade_token_secondary_oracle<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, SetTradeTokenSecondaryOracle>,
+        params: SetSecondaryOracleParams,
+    ) -> Result<()> {
+        handle_set_trade_token_secondary_oracle(ctx, params)
     }
 
     #[track_caller]
@@ -70,6 +165,30 @@ pub mod bumpin_trade {
         handle_initialize_market(ctx, params)
     }
 
+    #[track_caller]
+    pub fn modify_market<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, ModifyMarket>,
+        params: ModifyMarketParams,
+    ) -> Result<()> {
+        handle_modify_market(ctx, params)
+    }
+
+    #[track_caller]
+    pub fn set_market_secondary_oracle<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, SetMarketSecondaryOracle>,
+        params: SetSecondaryOracleParams,
+    ) -> Result<()> {
+        handle_set_market_secondary_oracle(ctx, params)
+    }
+
+    #[track_caller]
+    pub fn modify_pool<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, ModifyPool>,
+        params: ModifyPoolParams,
+    ) -> Result<()> {
+        handle_modify_pool(ctx, params)
+    }
+
     #[track_caller]
     pub fn initialize_rewards(ctx: Context<InitializePoolRewards>, _pool_index: u16) -> Result<()> {
         handle_initialize_rewards(ctx)
@@ -145,6 +264,37 @@ pub mod bumpin_trade {
         handle_place_wallet_order(ctx, order)
     }
 
+    #[track_caller]
+    pub fn amend_portfolio_order<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, PortfolioAmendOrder<'c>>,
+        params: AmendOrderParams,
+    ) -> Result<()> {
+        handle_amend_portfolio_order(ctx, params)
+    }
+
+    #[track_caller]
+    pub fn amend_wallet_order<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, WalletAmendOrder<'c>>,
+        params: AmendOrderParams,
+    ) -> Result<()> {
+        handle_amend_wallet_order(ctx, params)
+    }
+
+    #[track_caller]
+    pub fn place_orders<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, PlaceOrders<'c>>,
+        orders: Vec<PlaceOrderParams>,
+    ) -> Result<()> {
+        handle_place_orders(ctx, orders)
+    }
+
+    #[track_caller]
+    pub fn migrate_user_orders<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, MigrateUserOrders<'c>>,
+    ) -> Result<()> {
+        handle_migrate_user_orders(ctx)
+    }
+
     #[track_caller]
     pub fn execute_wallet_order<'a, 'b, 'c: 'info, 'info>(
         ctx: Context<'a, 'b, 'c, 'info, ExecuteWalletOrder<'c>>,
@@ -162,21 +312,61 @@ pub mod bumpin_trade {
     }
 
     #[track_caller]
-    pub fn portfolio_cancel_order(
-        ctx: Context<PortfolioCancelOrder>,
+    pub fn update_mark_price<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, UpdateMarkPrice<'info>>,
+        params: UpdateMarkPriceParams,
+    ) -> Result<()> {
+        handle_update_mark_price(ctx, params)
+    }
+
+    #[track_caller]
+    pub fn update_trailing_stop<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, UpdateTrailingStop<'info>>,
+        params: UpdateTrailingStopParams,
+    ) -> Result<()> {
+        handle_update_trailing_stop(ctx, params)
+    }
+
+    #[track_caller]
+    pub fn portfolio_cancel_order<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, PortfolioCancelOrder<'c>>,
         params: CancelOrderParams,
     ) -> Result<()> {
         handle_portfolio_cancel_order(ctx, params)
     }
 
     #[track_caller]
-    pub fn wallet_cancel_order(
-        ctx: Context<WalletCancelOrder>,
+    pub fn wallet_cancel_order<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, WalletCancelOrder<'c>>,
         params: CancelOrderParams,
     ) -> Result<()> {
         handle_wallet_cancel_order(ctx, params)
     }
 
+    #[track_caller]
+    pub fn cancel_all_orders<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, CancelAllOrders<'c>>,
+        params: CancelAllOrdersParams,
+    ) -> Result<()> {
+        handle_cancel_all_orders(ctx, params)
+    }
+
+    #[track_caller]
+    pub fn cancel_expired_order<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, CancelExpiredOrder<'c>>,
+        params: CancelExpiredOrderParams,
+    ) -> Result<()> {
+        handle_cancel_expired_order(ctx, params)
+    }
+
+    #[track_caller]
+    pub fn cancel_legacy_order<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, CancelLegacyOrder<'c>>,
+        params: CancelOrderParams,
+    ) -> Result<()> {
+        handle_cancel_legacy_order(ctx, params)
+    }
+
     #[track_caller]
     pub fn add_position_margin<'a, 'b, 'c: 'info, 'info>(
         ctx: Context<'a, 'b, 'c, 'info, AddPositionMargin>,
@@ -185,6 +375,14 @@ pub mod bumpin_trade {
         handle_add_position_margin(ctx, params)
     }
 
+    #[track_caller]
+    pub fn close_position<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, ClosePosition<'c>>,
+        params: ClosePositionParams,
+    ) -> Result<()> {
+        handle_close_position(ctx, params)
+    }
+
     #[track_caller]
     pub fn update_position_leverage<'a, 'b, 'c: 'info, 'info>(
         ctx: Context<'a, 'b, 'c, 'info, UpdatePositionLeverage>,
@@ -3,6 +3,7 @@ pub const PER_TOKEN_PRECISION: u128 = 10u128.pow(18);
 pub const PER_TOKEN_PRECISION_NUMBER: u16 = 18;
 pub const SMALL_RATE_TO_PER_TOKEN_PRECISION: u128 = 10u128.pow(8);
 pub const RATE_PRECISION: u128 = 10u128.pow(5);
+pub const BPS_PRECISION: u128 = 10u128.pow(4);
 pub const PRICE_PRECISION: u128 = 10u128.pow(8);
 pub const USD_PRECISION: u128 = 10u128.pow(10);
 pub const PRICE_TO_USD_PRECISION: u128 = 10u128.pow(2);
@@ -1,6 +1,6 @@
 use anchor_lang::prelude::*;
 
-use crate::errors::BumpResult;
+use crate::errors::{BumpErrorCode, BumpResult};
 use crate::state::market_map::MarketMap;
 use crate::state::oracle_map::OracleMap;
 use crate::state::pool_map::PoolMap;
@@ -21,9 +21,17 @@ pub fn load_maps<'a: 'info, 'info>(
 ) -> BumpResult<AccountMaps<'info>> {
     let market_map = MarketMap::load(remaining_accounts)?;
     let trade_token_map = TradeTokenMap::load(remaining_accounts)?;
-    let oracle_map = OracleMap::load(remaining_accounts)?;
+    let mut oracle_map = OracleMap::load(remaining_accounts)?;
     let pool_map = PoolMap::load(remaining_accounts)?;
     let vault_map = VaultMap::load(remaining_accounts)?;
 
+    for trade_token in trade_token_map.get_all_trade_token()? {
+        oracle_map.register_trade_token(&trade_token);
+    }
+    for market_loader in market_map.0.values() {
+        let market = market_loader.load().map_err(|_e| BumpErrorCode::CouldNotLoadMarketData)?;
+        oracle_map.register_market(&market);
+    }
+
     Ok(AccountMaps { market_map, trade_token_map, oracle_map, pool_map, vault_map })
 }
@@ -3,7 +3,7 @@ use crate::instructions::calculator;
 use crate::state::infrastructure::user_stake::UserStake;
 use crate::state::market_map::MarketMap;
 use crate::state::oracle_map::OracleMap;
-use crate::state::pool::Pool;
+use crate::state::pool::{Pool, PoolStatus};
 use crate::state::state::State;
 use crate::state::trade_token_map::TradeTokenMap;
 use crate::state::user::User;
@@ -18,6 +18,7 @@ pub fn un_stake(
     oracle_map: &mut OracleMap,
     market_map: &MarketMap,
 ) -> BumpResult<u128> {
+    validate!(!pool.status.eq(&PoolStatus::UnStakePaused), BumpErrorCode::UnStakePaused)?;
     let base_trade_token = trade_token_map.get_trade_token_by_mint_ref(&pool.mint_key)?;
     let stable_trade_token = trade_token_map.get_trade_token_by_mint_ref(&pool.stable_mint_key)?;
     let net_price = pool.get_pool_net_price(trade_token_map, oracle_map, market_map)?;
@@ -14,12 +14,15 @@ use crate::state::bump_events::{
     AddOrDecreaseMarginEvent, AddOrDeleteUserPositionEvent, UpdateUserPositionEvent,
 };
 use crate::state::infrastructure::user_order::{
-    OrderSide, OrderType, PositionSide, StopType, UserOrder,
+    OrderSide, OrderType, PositionSide, StopType, TimeInForce, UserOrder,
 };
 use crate::state::infrastructure::user_position::UserPosition;
-use crate::state::market::{Market, UpdateOIParams};
+use crate::state::market::{
+    CircuitBreakerAction, MarkPriceUsage, Market, MarketStatus, UpdateOIParams,
+};
 use crate::state::market_map::MarketMap;
 use crate::state::oracle_map::OracleMap;
+use crate::state::order_map::OrderMap;
 use crate::state::pool::Pool;
 use crate::state::pool_map::PoolMap;
 use crate::state::state::State;
@@ -27,9 +30,10 @@ use crate::state::trade_token::TradeToken;
 use crate::state::trade_token_map::TradeTokenMap;
 use crate::state::user::{User, UserTokenUpdateReason};
 use crate::state::vault_map::VaultMap;
-use crate::utils::{pda, token};
+use crate::utils::token;
 use crate::validate;
 
+//returns false when the order is left unexecuted because the index price deviates too much
 #[track_caller]
 pub fn handle_execute_order<'info>(
     user: &mut User,
@@ -43,10 +47,10 @@ pub fn handle_execute_order<'info>(
     program_id: &Pubkey,
     trade_token_map: &TradeTokenMap,
     oracle_map: &mut OracleMap,
+    order_map: &mut OrderMap,
     user_order: &UserOrder,
-) -> BumpResult<()> {
+) -> BumpResult<bool> {
     msg!("===========handle_execute_order start");
-    let user_key = user.key;
     let mut market = market_map.get_mut_ref(&user_order.symbol)?;
     let mut base_token_pool = pool_map.get_mut_ref(&market.pool_key)?;
     let mut stable_pool = pool_map.get_mut_ref(&market.stable_pool_key)?;
@@ -54,14 +58,34 @@ pub fn handle_execute_order<'info>(
     let mut stable_trade_token =
         trade_token_map.get_trade_token_by_mint_ref_mut(&market.stable_pool_mint_key)?;
 
+    let index_price_data = oracle_map
+        .get_price_data(&market.index_mint_oracle)
+        .map_err(|_e| BumpErrorCode::OracleNotFound)?;
+    let index_price = index_price_data.price;
+    //a trailing stop can only trigger on a print not older than its recorded extreme
+    validate!(
+        !user_order.order_type.eq(&OrderType::TRAILING_STOP)
+            || index_price_data.publish_time >= user_order.trailing_extreme_updated_at,
+        BumpErrorCode::OraclePriceToOld
+    )?;
+    //nothing is executed against a deviated print, returning instead of failing keeps the tripped
+    //breaker and the deviated print count, a reduce only breaker still lets decrease orders through
+    if !market.update_mark_price(index_price_data)? {
+        let reduce_only_breaker =
+            market.config.circuit_breaker_action == CircuitBreakerAction::ReduceOnly;
+        if !reduce_only_breaker || user_order.position_side.eq(&PositionSide::INCREASE) {
+            msg!("order {} not executed, index price deviates too much", user_order.order_id);
+            return Ok(false);
+        }
+    }
+    oracle_map.register_market(&market);
+
     //validate order
     validate_execute_order(&user_order, &market)?;
     let is_long = OrderSide::LONG == user_order.order_side;
     let execute_price = get_execution_price(
-        oracle_map
-            .get_price_data(&market.index_mint_oracle)
-            .map_err(|_e| BumpErrorCode::OracleNotFound)?
-            .price,
+        index_price,
+        market.get_mark_price(index_price, MarkPriceUsage::Trigger)?,
         &user_order,
     )?;
 
@@ -79,10 +103,10 @@ pub fn handle_execute_order<'info>(
         false => stable_pool.deref_mut().update_pool_borrowing_fee_rate()?,
     }
 
-    let position_key = pda::generate_position_key(
-        &user_key,
+    let position_key = user.get_position_key(
         market.symbol,
         user_order.is_portfolio_margin,
+        user_order.is_long_position(),
         program_id,
     )?;
     msg!("===========handle_execute_order start1111");
@@ -190,6 +214,18 @@ pub fn handle_execute_order<'info>(
                         true => vault_map.get_account(&trade_token.vault_key)?,
                         false => vault_map.get_account(&stable_trade_token.vault_key)?,
                     };
+                //a reduce only order never opens or flips a position, with nothing left to
+                //reduce it is canceled unfilled
+                if user_order.reduce_only
+                    && (user.get_position_size(&position_key) == 0u128
+                        || user.get_user_position_ref(&position_key)?.is_long == is_long)
+                {
+                    msg!(
+                        "reduce only order {} canceled, no position to reduce",
+                        user_order.order_id
+                    );
+                    return Ok(true);
+                }
                 let position = user.get_user_position_ref(&position_key)?;
                 let position_side = position.is_long;
                 if position.position_size == 0u128 {
@@ -198,6 +234,11 @@ pub fn handle_execute_order<'info>(
                 if position_side == is_long {
                     return Err(BumpErrorCode::InvalidParam.into());
                 }
+                validate!(
+                    user_order.time_in_force != TimeInForce::FOK
+                        || position.position_size >= user_order.order_size,
+                    BumpErrorCode::OrderNotFullyFillable
+                )?;
                 msg!("===========handle_execute_order start2222");
                 decrease_position(
                     DecreasePositionParams {
@@ -230,15 +271,14 @@ pub fn handle_execute_order<'info>(
                     bump_signer,
                     token_program,
                     oracle_map,
+                    order_map,
                     &position_key,
                 )?;
                 Ok(())
             }
         },
     }?;
-    //delete order
-    user.delete_order(user_order.order_id)?;
-    Ok(())
+    Ok(true)
 }
 
 pub fn use_base_token(position_side: &PositionSide, order_side: &OrderSide) -> BumpResult<bool> {
@@ -294,8 +334,14 @@ fn execute_increase_order_margin(
 }
 
 #[track_caller]
-fn get_execution_price(index_price: u128, order: &UserOrder) -> BumpResult<u128> {
-    if order.order_type.eq(&OrderType::MARKET) {
+//trigger orders compare the trigger price with the market's trigger mark price but fill at index price
+fn get_execution_price(
+    index_price: u128,
+    trigger_index_price: u128,
+    order: &UserOrder,
+) -> BumpResult<u128> {
+    //a twap slice fills at market, bounded by the acceptable price of the order
+    if order.order_type.eq(&OrderType::MARKET) || order.order_type.eq(&OrderType::TWAP) {
         if order.acceptable_price > 0 {
             if order.order_side.eq(&OrderSide::LONG) && index_price >= order.acceptable_price {
                 return Err(BumpErrorCode::PriceIsNotAllowed);
@@ -311,8 +357,17 @@ fn get_execution_price(index_price: u128, order: &UserOrder) -> BumpResult<u128>
     if order.order_type.eq(&OrderType::LIMIT)
         || (order.order_type.eq(&OrderType::STOP) && order.stop_type.eq(&StopType::TakeProfit))
     {
-        if (long && order.trigger_price >= index_price)
-            || (!long && order.trigger_price <= index_price)
+        if (long && order.trigger_price >= trigger_index_price)
+            || (!long && order.trigger_price <= trigger_index_price)
+        {
+            return Ok(index_price);
+        }
+        return Err(BumpErrorCode::PriceIsNotAllowed);
+    }
+    if order.order_type.eq(&OrderType::TRAILING_STOP) {
+        let trailing_trigger_price = order.get_trailing_trigger_price()?;
+        if (long && trigger_index_price >= trailing_trigger_price)
+            || (!long && trigger_index_price <= trailing_trigger_price)
         {
             return Ok(index_price);
         }
@@ -320,8 +375,8 @@ fn get_execution_price(index_price: u128, order: &UserOrder) -> BumpResult<u128>
     }
     if order.order_type.eq(&OrderType::STOP)
         && order.stop_type.eq(&StopType::StopLoss)
-        && ((long && order.trigger_price <= index_price)
-            || (!long && order.trigger_price >= index_price))
+        && ((long && order.trigger_price <= trigger_index_price)
+            || (!long && order.trigger_price >= trigger_index_price))
     {
         return Ok(index_price);
     }
@@ -331,8 +386,23 @@ fn get_execution_price(index_price: u128, order: &UserOrder) -> BumpResult<u128>
 
 #[track_caller]
 fn validate_execute_order(order: &UserOrder, market: &Market) -> BumpResult<()> {
-    if order.leverage > market.config.maximum_leverage
-        || order.leverage < market.config.minimum_leverage
+    let now = calculator::current_time();
+    validate!(!order.is_expired(now), BumpErrorCode::OrderExpired)?;
+    validate!(
+        !order.order_type.eq(&OrderType::TWAP) || order.is_twap_slice_due(now)?,
+        BumpErrorCode::TwapSliceNotDue
+    )?;
+    match market.market_status {
+        MarketStatus::Pause => return Err(BumpErrorCode::MarketPaused),
+        MarketStatus::ReduceOnly if order.position_side.eq(&PositionSide::INCREASE) => {
+            return Err(BumpErrorCode::MarketReduceOnly)
+        },
+        _ => {},
+    }
+    //bounds tightened by modify_market apply to new exposure, open positions can always be closed
+    if order.position_side.eq(&PositionSide::INCREASE)
+        && (order.leverage > market.config.maximum_leverage
+            || order.leverage < market.config.minimum_leverage)
     {
         return Err(BumpErrorCode::LeverageIsNotAllowed);
     }
@@ -429,8 +499,9 @@ pub fn decrease_position<'info>(
     bump_signer: &AccountInfo<'info>,
     token_program: &Program<'info, Token>,
     oracle_map: &mut OracleMap,
+    order_map: &mut OrderMap,
     position_key: &Pubkey,
-) -> BumpResult<()> {
+) -> BumpResult<UpdateDecreaseResponse> {
     let (is_long, position_deletion, pre_position, response) = {
         let position = user.get_user_position_mut_ref(position_key)?;
         let pre_position = *position;
@@ -467,7 +538,13 @@ pub fn decrease_position<'info>(
             return Err(BumpErrorCode::PositionShouldBeLiquidation);
         }
         let position_deletion = if params.decrease_size != position.position_size {
-            update_decrease_position(position, params.decrease_size, &response)?;
+            update_decrease_position(
+                position,
+                params.decrease_size,
+                &response,
+                market,
+                state_account,
+            )?;
             false
         } else {
             true
@@ -476,6 +553,8 @@ pub fn decrease_position<'info>(
     };
 
     if position_deletion {
+        //a reduce-only order left open would fire against the next position of the same side
+        order_map.validate_all_orders_loaded(user, order_map.executing_order_id)?;
         user.delete_position(position_key)?;
     }
     //collect fee
@@ -509,12 +588,14 @@ pub fn decrease_position<'info>(
         &pre_position,
     )?;
 
-    //cancel stop order
-    user.cancel_stop_orders(
+    //cancel the stop orders of the position passed in the remaining accounts
+    order_map.cancel_stop_orders(
+        user,
         params.order_id,
         pre_position.symbol,
         &pre_position.margin_mint_key,
         pre_position.is_portfolio_margin,
+        if user.hedge_mode { Some(pre_position.is_long) } else { None },
     )?;
 
     //add insurance fund
@@ -530,7 +611,7 @@ pub fn decrease_position<'info>(
         )?;
     }
 
-    Ok(())
+    Ok(response)
 }
 
 #[track_caller]
@@ -595,6 +676,8 @@ fn update_decrease_position(
     position: &mut UserPosition,
     decrease_size: u128,
     response: &UpdateDecreaseResponse,
+    market: &Market,
+    state: &State,
 ) -> BumpResult {
     let pre_position = *position;
     position.sub_position_size(decrease_size)?;
@@ -609,6 +692,7 @@ fn update_decrease_position(
     position.sub_realized_funding_fee(response.settle_funding_fee)?;
     position.sub_realized_funding_fee_usd(response.settle_funding_fee_in_usd)?;
     position.sub_close_fee_usd(response.settle_close_fee_in_usd)?;
+    position.set_mm_usd(position.get_position_mm(market, state)?)?;
     position.set_last_update(calculator::current_time())?;
     emit!(UpdateUserPositionEvent { pre_position, position: *position });
     Ok(())
@@ -1206,10 +1290,10 @@ pub fn increase_position(
     let mut trade_token = trade_token_map.get_trade_token_by_mint_ref_mut(&market.pool_mint_key)?;
     let mut stable_trade_token =
         trade_token_map.get_trade_token_by_mint_ref_mut(&market.stable_pool_mint_key)?;
-    let position_key = pda::generate_position_key(
-        &user.key,
+    let position_key = user.get_position_key(
         market.symbol,
         order.is_portfolio_margin,
+        order.is_long_position(),
         program_id,
     )?;
 
@@ -4,7 +4,7 @@ use crate::math::safe_math::SafeMath;
 use crate::processor::{fee_processor, user_processor};
 use crate::state::infrastructure::user_stake::UserStakeStatus;
 use crate::state::oracle_map::OracleMap;
-use crate::state::pool::Pool;
+use crate::state::pool::{Pool, PoolStatus};
 use crate::state::trade_token_map::TradeTokenMap;
 use crate::state::user::User;
 use crate::{price, validate};
@@ -16,6 +16,7 @@ pub fn stake(
     oracles: &mut OracleMap,
     requested_token_amount: u128,
 ) -> BumpResult<u128> {
+    validate!(!pool.status.eq(&PoolStatus::StakePaused), BumpErrorCode::StakePaused)?;
     let trade_token = trade_tokens.get_trade_token_by_mint_ref(&pool.mint_key).unwrap();
     let token_price = price!(trade_token, oracles);
 
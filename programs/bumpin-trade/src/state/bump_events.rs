@@ -8,7 +8,11 @@ use crate::state::infrastructure::user_order::UserOrder;
 use crate::state::infrastructure::user_position::UserPosition;
 use crate::state::infrastructure::user_stake::{UserRewards, UserStake};
 use crate::state::infrastructure::user_token::UserToken;
-use crate::state::pool::PoolBalance;
+use crate::state::market::{MarketConfig, MarketStatus};
+use crate::state::oracle::{
+    OracleAggregation, OraclePriceSource, OracleSource, OracleValidity, SecondaryOracle,
+};
+use crate::state::pool::{PoolBalance, PoolConfig, PoolStatus};
 use crate::state::user::UserTokenUpdateReason;
 
 #[event]
@@ -70,6 +74,20 @@ pub struct AddOrDeleteUserOrderEvent {
     pub is_add: bool,
 }
 
+#[event]
+pub struct AmendUserOrderEvent {
+    pub user_key: Pubkey,
+    pub pre_order: UserOrder,
+    pub order: UserOrder,
+}
+
+#[event]
+pub struct TwapOrderSliceEvent {
+    pub user_key: Pubkey,
+    pub order: UserOrder,
+    pub slice_size: u128,
+}
+
 #[event]
 pub struct UpdateUserPositionEvent {
     pub pre_position: UserPosition,
@@ -118,3 +136,110 @@ pub struct PoolUpdateEvent {
     pub pre_apr: u128,
     pub pre_insurance_fund_amount: u128,
 }
+
+#[event]
+pub struct MarketConfigUpdateEvent {
+    pub market_key: Pubkey,
+    pub symbol: [u8; 32],
+    pub pre_config: MarketConfig,
+    pub config: MarketConfig,
+    pub pre_market_status: MarketStatus,
+    pub market_status: MarketStatus,
+    pub pre_index_mint_oracle: Pubkey,
+    pub index_mint_oracle: Pubkey,
+    pub pre_index_oracle_source: OracleSource,
+    pub index_oracle_source: OracleSource,
+    pub pre_index_oracle_feed_id: [u8; 32],
+    pub index_oracle_feed_id: [u8; 32],
+    pub pre_oracle_validity: OracleValidity,
+    pub oracle_validity: OracleValidity,
+}
+
+#[event]
+pub struct PoolConfigUpdateEvent {
+    pub pool_key: Pubkey,
+    pub pool_mint: Pubkey,
+    pub pre_config: PoolConfig,
+    pub config: PoolConfig,
+    pub pre_status: PoolStatus,
+    pub status: PoolStatus,
+}
+
+#[event]
+pub struct TradeTokenConfigUpdateEvent {
+    pub trade_token_key: Pubkey,
+    pub mint_key: Pubkey,
+    pub pre_discount: u32,
+    pub discount: u32,
+    pub pre_liquidation_factor: u32,
+    pub liquidation_factor: u32,
+    pub pre_oracle_key: Pubkey,
+    pub oracle_key: Pubkey,
+    pub pre_oracle_source: OracleSource,
+    pub oracle_source: OracleSource,
+    pub pre_oracle_feed_id: [u8; 32],
+    pub oracle_feed_id: [u8; 32],
+    pub pre_oracle_validity: OracleValidity,
+    pub oracle_validity: OracleValidity,
+}
+
+#[event]
+pub struct AdminUpdateEvent {
+    pub pre_admin: Pubkey,
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct KeeperUpdateEvent {
+    pub pre_keeper_key: Pubkey,
+    pub keeper_key: Pubkey,
+}
+
+#[event]
+pub struct KeeperRolesUpdateEvent {
+    pub keeper_key: Pubkey,
+    pub pre_roles: u8,
+    pub roles: u8,
+}
+
+#[event]
+pub struct LiquidationEvent {
+    pub user_key: Pubkey,
+    pub position_key: Pubkey,
+    pub liquidator: Pubkey,
+    pub symbol: [u8; 32],
+    pub margin_mint_key: Pubkey,
+    pub is_long: bool,
+    pub is_portfolio_margin: bool,
+    pub position_size: u128,
+    pub liquidation_price: u128,
+    pub index_price: u128,
+    pub liquidator_fee: u128,
+}
+
+#[event]
+pub struct SecondaryOracleUpdateEvent {
+    pub key: Pubkey,
+    pub oracle_key: Pubkey,
+    pub pre_secondary_oracle: SecondaryOracle,
+    pub secondary_oracle: SecondaryOracle,
+}
+
+#[event]
+pub struct OraclePriceAggregationEvent {
+    pub oracle_key: Pubkey,
+    pub price_oracle_key: Pubkey,
+    pub aggregation: OracleAggregation,
+    pub price_source: OraclePriceSource,
+    pub price: u128,
+    pub confidence: u128,
+}
+
+#[event]
+pub struct PriceCircuitBreakerEvent {
+    pub symbol: [u8; 32],
+    pub index_price: u128,
+    pub reference_price: u128,
+    pub tripped: bool,
+    pub market_status: MarketStatus,
+}
@@ -1,8 +1,54 @@
 use anchor_lang::prelude::*;
 use bumpin_trade_attribute::bumpin_zero_copy_unsafe;
 
+use crate::errors::{BumpErrorCode, BumpResult};
+use crate::math::constants::BPS_PRECISION;
+use crate::math::safe_math::SafeMath;
+use crate::validate;
+
 #[bumpin_zero_copy_unsafe]
 pub struct UserOrder {
+    pub order_margin: u128,
+    pub order_size: u128,
+    pub trigger_price: u128,
+    pub acceptable_price: u128,
+    pub take_profit_price: u128,
+    pub stop_loss_price: u128,
+    pub trailing_callback: u128,
+    pub trailing_extreme_price: u128,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub order_id: u64,
+    pub parent_order_id: u64,
+    pub trailing_extreme_updated_at: i64,
+    pub execution_fee: u64,
+    pub twap_interval: i64,
+    pub twap_last_executed_at: i64,
+    pub margin_mint_key: Pubkey,
+    pub authority: Pubkey,
+    pub user_token_account: Pubkey,
+    pub symbol: [u8; 32],
+    pub leverage: u32,
+    pub twap_slice_count: u16,
+    pub twap_executed_slices: u16,
+    pub order_side: OrderSide,
+    pub position_side: PositionSide,
+    pub order_type: OrderType,
+    pub stop_type: StopType,
+    pub status: OrderStatus,
+    pub is_portfolio_margin: bool,
+    pub time_in_force: TimeInForce,
+    pub is_bracket: bool,
+    pub trailing_callback_type: TrailingCallbackType,
+    pub reduce_only: bool,
+    pub padding: [u8; 14],
+}
+
+/// Order layout of the first release, when up to 8 orders were stored in the user account.
+/// The array stays in `User` so the fields after it keep their offsets, its live orders are
+/// moved to order accounts by `migrate_user_orders`.
+#[bumpin_zero_copy_unsafe]
+pub struct LegacyUserOrder {
     pub order_margin: u128,
     pub order_size: u128,
     pub trigger_price: u128,
@@ -24,6 +70,35 @@ pub struct UserOrder {
     pub reserve_padding: [u8; 16],
 }
 
+impl LegacyUserOrder {
+    pub fn is_live(&self) -> bool {
+        !self.status.eq(&OrderStatus::INIT)
+    }
+
+    pub fn to_user_order(&self) -> UserOrder {
+        UserOrder {
+            order_margin: self.order_margin,
+            order_size: self.order_size,
+            trigger_price: self.trigger_price,
+            acceptable_price: self.acceptable_price,
+            created_at: self.created_at,
+            order_id: self.order_id,
+            margin_mint_key: self.margin_mint_key,
+            authority: self.authority,
+            user_token_account: self.user_token_account,
+            symbol: self.symbol,
+            leverage: self.leverage,
+            order_side: self.order_side,
+            position_side: self.position_side,
+            order_type: self.order_type,
+            stop_type: self.stop_type,
+            status: self.status,
+            is_portfolio_margin: self.is_portfolio_margin,
+            ..Default::default()
+        }
+    }
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone, Default, PartialEq, Debug, Eq)]
 pub enum OrderSide {
     #[default]
@@ -37,6 +112,8 @@ pub enum OrderStatus {
     #[default]
     INIT,
     USING,
+    /// bracket order waiting for its parent increase order to be executed
+    PENDING,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone, Default, PartialEq, Debug, Eq)]
@@ -54,6 +131,10 @@ pub enum OrderType {
     MARKET,
     LIMIT,
     STOP,
+    #[allow(non_camel_case_types)]
+    TRAILING_STOP,
+    /// executed by keepers in slices of an even share of the remaining size
+    TWAP,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone, Default, PartialEq, Debug, Eq)]
@@ -64,7 +145,149 @@ pub enum StopType {
     TakeProfit,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone, Default, PartialEq, Debug, Eq)]
+pub enum TimeInForce {
+    /// good till canceled
+    #[default]
+    GTC,
+    /// good till `expires_at`
+    GTT,
+    /// market only, fill what the position allows or fail
+    IOC,
+    /// market only, fill the whole size or fail
+    FOK,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Copy, Clone, Default, PartialEq, Debug, Eq)]
+pub enum TrailingCallbackType {
+    /// `trailing_callback` is a share of the extreme price, in basis points
+    #[default]
+    Bps,
+    /// `trailing_callback` is an absolute price distance
+    Price,
+}
+
 impl UserOrder {
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at > 0 && now >= self.expires_at
+    }
+
+    /// Side of the position the order opens or closes.
+    pub fn is_long_position(&self) -> bool {
+        self.order_side.eq(&OrderSide::LONG) == self.position_side.eq(&PositionSide::INCREASE)
+    }
+
+    pub fn has_bracket_orders(&self) -> bool {
+        !self.is_bracket && (self.take_profit_price > 0u128 || self.stop_loss_price > 0u128)
+    }
+
+    /// The bracket orders of an increase order take the two ids following it.
+    pub fn get_bracket_order_id(&self, stop_type: StopType) -> BumpResult<u64> {
+        match stop_type {
+            StopType::TakeProfit => self.order_id.safe_add(1),
+            StopType::StopLoss => self.order_id.safe_add(2),
+            StopType::NONE => Err(BumpErrorCode::InvalidParam),
+        }
+    }
+
+    /// Folds an oracle trigger price into the high-water (closing a long) or low-water
+    /// (closing a short) mark of a trailing stop. Prints older than the last one folded in
+    /// are rejected, so a keeper can not replay a past price update to move the mark.
+    pub fn update_trailing_extreme(
+        &mut self,
+        trigger_index_price: u128,
+        publish_time: i64,
+    ) -> BumpResult {
+        validate!(
+            publish_time > self.trailing_extreme_updated_at,
+            BumpErrorCode::OraclePriceToOld
+        )?;
+        let is_new_extreme = match self.order_side {
+            OrderSide::SHORT => trigger_index_price > self.trailing_extreme_price,
+            OrderSide::LONG => trigger_index_price < self.trailing_extreme_price,
+            OrderSide::NONE => return Err(BumpErrorCode::InvalidParam),
+        };
+        if is_new_extreme {
+            self.trailing_extreme_price = trigger_index_price;
+        }
+        self.trailing_extreme_updated_at = publish_time;
+        Ok(())
+    }
+
+    pub fn get_trailing_trigger_price(&self) -> BumpResult<u128> {
+        let callback = match self.trailing_callback_type {
+            TrailingCallbackType::Bps => self
+                .trailing_extreme_price
+                .safe_mul(self.trailing_callback)?
+                .safe_div(BPS_PRECISION)?,
+            TrailingCallbackType::Price => self.trailing_callback,
+        };
+        match self.order_side {
+            OrderSide::SHORT => self.trailing_extreme_price.safe_sub(callback),
+            OrderSide::LONG => self.trailing_extreme_price.safe_add(callback),
+            OrderSide::NONE => Err(BumpErrorCode::InvalidParam),
+        }
+    }
+
+    /// The other leg of a take-profit / stop-loss pair, which is canceled once this one fills.
+    pub fn get_linked_order_id(&self) -> BumpResult<Option<u64>> {
+        if !self.is_bracket || self.take_profit_price == 0u128 || self.stop_loss_price == 0u128 {
+            return Ok(None);
+        }
+        let parent_order_id = self.parent_order_id;
+        match self.stop_type {
+            StopType::TakeProfit => Ok(Some(parent_order_id.safe_add(2)?)),
+            StopType::StopLoss => Ok(Some(parent_order_id.safe_add(1)?)),
+            StopType::NONE => Err(BumpErrorCode::InvalidParam),
+        }
+    }
+
+    /// Whether the order passes the optional symbol and side filters of a bulk cancel.
+    pub fn is_matched(&self, symbol: &Option<[u8; 32]>, order_side: &Option<OrderSide>) -> bool {
+        let symbol_matched = match symbol {
+            Some(symbol) => self.symbol == *symbol,
+            None => true,
+        };
+        let order_side_matched = match order_side {
+            Some(order_side) => self.order_side.eq(order_side),
+            None => true,
+        };
+        symbol_matched && order_side_matched
+    }
+
+    /// A twap order can run its first slice right away, then one slice per interval.
+    pub fn is_twap_slice_due(&self, now: i64) -> BumpResult<bool> {
+        Ok(self.twap_executed_slices == 0
+            || now >= self.twap_last_executed_at.safe_add(self.twap_interval)?)
+    }
+
+    /// The next slice of a twap order takes an even share of the remaining size, margin and
+    /// execution fee, so the last slice takes whatever rounding left over.
+    pub fn get_twap_slice(&self) -> BumpResult<UserOrder> {
+        let remaining_slices = self.twap_slice_count.safe_sub(self.twap_executed_slices)?;
+        validate!(remaining_slices > 0, BumpErrorCode::InvalidParam)?;
+        Ok(UserOrder {
+            order_size: self.order_size.safe_div(remaining_slices.into())?,
+            order_margin: self.order_margin.safe_div(remaining_slices.into())?,
+            execution_fee: self.execution_fee.safe_div(remaining_slices.into())?,
+            ..*self
+        })
+    }
+
+    /// Records an executed slice, the order keeps what is left for the following slices.
+    pub fn record_twap_slice(&mut self, slice: &UserOrder, now: i64) -> BumpResult {
+        self.order_size = self.order_size.safe_sub(slice.order_size)?;
+        self.order_margin = self.order_margin.safe_sub(slice.order_margin)?;
+        self.execution_fee = self.execution_fee.safe_sub(slice.execution_fee)?;
+        self.twap_executed_slices = self.twap_executed_slices.safe_add(1)?;
+        self.twap_last_executed_at = now;
+        Ok(())
+    }
+
+    pub fn is_twap_completed(&self) -> bool {
+        self.twap_executed_slices >= self.twap_slice_count
+    }
+
     pub fn set_leverage(&mut self, leverage: u32) {
         self.leverage = leverage;
     }
@@ -72,25 +295,163 @@ impl UserOrder {
     pub fn print(&self) {
         let symbol_str = std::str::from_utf8(&self.symbol).unwrap_or("Invalid UTF-8");
         msg!(
-            "Order Margin: {}, Order Size: {}, Trigger Price: {}, Acceptable Price: {}, Created At: {}, Order ID: {}, Margin Mint Key: {}, Authority: {}, Symbol: {}, Leverage: {}, Order Side: {:?}, Position Side: {:?}, Order Type: {:?}, Stop Type: {:?}, Status: {:?}, Is Portfolio Margin: {}, Padding: {:?}, Reserve Padding: {:?}",
+            "Order Margin: {}, Order Size: {}, Trigger Price: {}, Acceptable Price: {}, Take Profit Price: {}, Stop Loss Price: {}, Trailing Callback: {}, Trailing Extreme Price: {}, Created At: {}, Expires At: {}, Order ID: {}, Parent Order ID: {}, Trailing Extreme Updated At: {}, Execution Fee: {}, Twap Interval: {}, Twap Last Executed At: {}, Margin Mint Key: {}, Authority: {}, Symbol: {}, Leverage: {}, Twap Slice Count: {}, Twap Executed Slices: {}, Order Side: {:?}, Position Side: {:?}, Order Type: {:?}, Stop Type: {:?}, Status: {:?}, Is Portfolio Margin: {}, Time In Force: {:?}, Is Bracket: {}, Trailing Callback Type: {:?}, Reduce Only: {}, Padding: {:?}",
             self.order_margin,
             self.order_size,
             self.trigger_price,
             self.acceptable_price,
+            self.take_profit_price,
+            self.stop_loss_price,
+            self.trailing_callback,
+            self.trailing_extreme_price,
             self.created_at,
+            self.expires_at,
             self.order_id,
+            self.parent_order_id,
+            self.trailing_extreme_updated_at,
+            self.execution_fee,
+            self.twap_interval,
+            self.twap_last_executed_at,
             self.margin_mint_key,
             self.authority,
             symbol_str,
             self.leverage,
+            self.twap_slice_count,
+            self.twap_executed_slices,
             self.order_side,
             self.position_side,
             self.order_type,
             self.stop_type,
             self.status,
             self.is_portfolio_margin,
+            self.time_in_force,
+            self.is_bracket,
+            self.trailing_callback_type,
+            self.reduce_only,
             self.padding,
-            self.reserve_padding,
         );
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::math::constants::BPS_PRECISION;
+    use crate::state::infrastructure::user_order::{
+        OrderSide, StopType, TrailingCallbackType, UserOrder,
+    };
+
+    #[test]
+    fn test_get_linked_order_id() {
+        let mut order = UserOrder {
+            parent_order_id: 10,
+            take_profit_price: 110,
+            stop_loss_price: 90,
+            stop_type: StopType::TakeProfit,
+            is_bracket: true,
+            ..Default::default()
+        };
+        assert_eq!(order.get_linked_order_id().unwrap(), Some(12));
+        order.stop_type = StopType::StopLoss;
+        assert_eq!(order.get_linked_order_id().unwrap(), Some(11));
+        order.stop_type = StopType::NONE;
+        assert!(order.get_linked_order_id().is_err());
+
+        //a single bracket leg has nothing to cancel
+        order.stop_type = StopType::StopLoss;
+        order.take_profit_price = 0;
+        assert_eq!(order.get_linked_order_id().unwrap(), None);
+        order.take_profit_price = 110;
+        order.is_bracket = false;
+        assert_eq!(order.get_linked_order_id().unwrap(), None);
+    }
+
+    #[test]
+    fn test_update_trailing_extreme() {
+        //closing a long tracks the highest price
+        let mut order = UserOrder {
+            order_side: OrderSide::SHORT,
+            trailing_extreme_price: 100,
+            trailing_extreme_updated_at: 10,
+            ..Default::default()
+        };
+        order.update_trailing_extreme(120, 11).unwrap();
+        assert_eq!(order.trailing_extreme_price, 120);
+        order.update_trailing_extreme(110, 12).unwrap();
+        assert_eq!(order.trailing_extreme_price, 120);
+        assert_eq!(order.trailing_extreme_updated_at, 12);
+        //a replayed print can not move the mark
+        assert!(order.update_trailing_extreme(130, 12).is_err());
+        assert_eq!(order.trailing_extreme_price, 120);
+
+        //closing a short tracks the lowest price
+        let mut order = UserOrder {
+            order_side: OrderSide::LONG,
+            trailing_extreme_price: 100,
+            ..Default::default()
+        };
+        order.update_trailing_extreme(90, 1).unwrap();
+        assert_eq!(order.trailing_extreme_price, 90);
+        order.update_trailing_extreme(95, 2).unwrap();
+        assert_eq!(order.trailing_extreme_price, 90);
+
+        order.order_side = OrderSide::NONE;
+        assert!(order.update_trailing_extreme(80, 3).is_err());
+    }
+
+    #[test]
+    fn test_get_trailing_trigger_price() {
+        let mut order = UserOrder {
+            order_side: OrderSide::SHORT,
+            trailing_extreme_price: 200,
+            trailing_callback: BPS_PRECISION / 10,
+            trailing_callback_type: TrailingCallbackType::Bps,
+            ..Default::default()
+        };
+        assert_eq!(order.get_trailing_trigger_price().unwrap(), 180);
+        order.order_side = OrderSide::LONG;
+        assert_eq!(order.get_trailing_trigger_price().unwrap(), 220);
+
+        order.trailing_callback = 5;
+        order.trailing_callback_type = TrailingCallbackType::Price;
+        assert_eq!(order.get_trailing_trigger_price().unwrap(), 205);
+        order.order_side = OrderSide::SHORT;
+        assert_eq!(order.get_trailing_trigger_price().unwrap(), 195);
+
+        order.order_side = OrderSide::NONE;
+        assert!(order.get_trailing_trigger_price().is_err());
+    }
+
+    #[test]
+    fn test_twap_slices() {
+        let mut order = UserOrder {
+            order_size: 1_000,
+            order_margin: 100,
+            execution_fee: 30,
+            twap_slice_count: 3,
+            twap_interval: 60,
+            ..Default::default()
+        };
+        assert!(order.is_twap_slice_due(0).unwrap());
+
+        let slice = order.get_twap_slice().unwrap();
+        assert_eq!((slice.order_size, slice.order_margin, slice.execution_fee), (333, 33, 10));
+        order.record_twap_slice(&slice, 100).unwrap();
+        assert_eq!((order.order_size, order.order_margin, order.execution_fee), (667, 67, 20));
+        assert_eq!(order.twap_executed_slices, 1);
+        assert!(!order.is_twap_slice_due(159).unwrap());
+        assert!(order.is_twap_slice_due(160).unwrap());
+
+        let slice = order.get_twap_slice().unwrap();
+        assert_eq!((slice.order_size, slice.order_margin, slice.execution_fee), (333, 33, 10));
+        order.record_twap_slice(&slice, 160).unwrap();
+        assert!(!order.is_twap_completed());
+
+        //the last slice takes what rounding left over
+        let slice = order.get_twap_slice().unwrap();
+        assert_eq!((slice.order_size, slice.order_margin, slice.execution_fee), (334, 34, 10));
+        order.record_twap_slice(&slice, 220).unwrap();
+        assert!(order.is_twap_completed());
+        assert_eq!((order.order_size, order.order_margin, order.execution_fee), (0, 0, 0));
+        assert!(order.get_twap_slice().is_err());
+    }
+}
@@ -0,0 +1,97 @@
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use arrayref::array_ref;
+
+use bumpin_trade_attribute::bumpin_zero_copy_unsafe;
+
+use crate::errors::{BumpErrorCode, BumpResult};
+use crate::state::bump_events::KeeperRolesUpdateEvent;
+use crate::traits::Size;
+
+pub const MAX_KEEPERS: usize = 16;
+
+#[account(zero_copy(unsafe))]
+#[derive(Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct KeeperRegistry {
+    pub keepers: [Keeper; MAX_KEEPERS],
+    pub reserve_padding: [u8; 32],
+}
+
+impl Size for KeeperRegistry {
+    const SIZE: usize = std::mem::size_of::<KeeperRegistry>() + 8;
+}
+
+#[bumpin_zero_copy_unsafe]
+pub struct Keeper {
+    pub key: Pubkey,
+    pub roles: u8,
+    pub padding: [u8; 7],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Eq, PartialEq)]
+//liquidation is permissionless, its former bit 0b00010 stays unused
+pub enum KeeperRole {
+    ExecuteOrder = 0b00001,
+    ADL = 0b00100,
+    Rewards = 0b01000,
+    Rebalance = 0b10000,
+}
+
+impl KeeperRole {
+    pub fn mask(self) -> u8 {
+        self as u8
+    }
+}
+
+impl KeeperRegistry {
+    /// Roles of `key` in the registry account, none while the registry is not initialized.
+    pub fn has_role_in_account(
+        keeper_registry: &AccountInfo,
+        key: &Pubkey,
+        role: KeeperRole,
+    ) -> BumpResult<bool> {
+        if !keeper_registry.owner.eq(&crate::id()) {
+            return Ok(false);
+        }
+        let data = keeper_registry
+            .try_borrow_data()
+            .map_err(|_e| BumpErrorCode::CouldNotLoadKeeperRegistry)?;
+        if data.len() < KeeperRegistry::SIZE
+            || !array_ref![data, 0, 8].eq(&KeeperRegistry::discriminator())
+        {
+            return Err(BumpErrorCode::CouldNotLoadKeeperRegistry);
+        }
+        let keeper_registry: &KeeperRegistry = bytemuck::from_bytes(&data[8..KeeperRegistry::SIZE]);
+        Ok(keeper_registry.has_role(key, role))
+    }
+
+    pub fn has_role(&self, key: &Pubkey, role: KeeperRole) -> bool {
+        self.keepers.iter().any(|keeper| keeper.key.eq(key) && keeper.roles & role.mask() != 0)
+    }
+
+    pub fn get_roles(&self, key: &Pubkey) -> u8 {
+        self.keepers.iter().find(|keeper| keeper.key.eq(key)).map_or(0u8, |keeper| keeper.roles)
+    }
+
+    /// set roles of a keeper, roles = 0 removes the keeper from the registry
+    pub fn set_keeper(&mut self, key: &Pubkey, roles: u8) -> BumpResult {
+        let pre_roles = self.get_roles(key);
+        match self.keepers.iter_mut().find(|keeper| keeper.key.eq(key)) {
+            Some(keeper) if roles == 0u8 => *keeper = Keeper::default(),
+            Some(keeper) => keeper.roles = roles,
+            None if roles == 0u8 => {},
+            None => {
+                let keeper = self
+                    .keepers
+                    .iter_mut()
+                    .find(|keeper| keeper.key.eq(&Pubkey::default()))
+                    .ok_or(BumpErrorCode::NoMoreKeeperSpace)?;
+                keeper.key = *key;
+                keeper.roles = roles;
+            },
+        }
+        emit!(KeeperRolesUpdateEvent { keeper_key: *key, pre_roles, roles });
+        Ok(())
+    }
+}
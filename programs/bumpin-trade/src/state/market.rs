@@ -1,9 +1,11 @@
 use crate::errors::{BumpErrorCode, BumpResult};
 use crate::instructions::calculator;
 use crate::math::casting::Cast;
-use crate::math::constants::{PRICE_PRECISION, SMALL_RATE_TO_PER_TOKEN_PRECISION};
+use crate::math::constants::{BPS_PRECISION, PRICE_PRECISION, SMALL_RATE_TO_PER_TOKEN_PRECISION};
 use crate::math::safe_math::SafeMath;
+use crate::state::bump_events::PriceCircuitBreakerEvent;
 use crate::state::infrastructure::market_funding_fee::MarketFundingFee;
+use crate::state::oracle::{OraclePriceData, OracleSource, OracleValidity, SecondaryOracle};
 use crate::state::oracle_map::OracleMap;
 use crate::state::state::State;
 use crate::traits::Size;
@@ -26,8 +28,20 @@ pub struct Market {
     pub stable_pool_mint_key: Pubkey,
     pub index: u16,
     pub market_status: MarketStatus,
-    pub padding: [u8; 13],
-    pub reserve_padding: [u8; 32],
+    pub index_oracle_source: OracleSource,
+    pub price_circuit_breaker_tripped: bool,
+    pub padding: [u8; 11],
+    pub oracle_validity: OracleValidity,
+    pub reserve_padding: [u8; 16],
+    //fields below were appended after the first release, see migrate_market
+    pub index_oracle_feed_id: [u8; 32],
+    pub index_secondary_oracle: SecondaryOracle,
+    pub mark_price: MarkPrice,
+    pub ema_window_secs: u32,
+    pub max_price_deviation_bps: u32,
+    pub price_reanchor_count: u16,
+    pub deviated_price_count: u16,
+    pub mark_price_padding: [u8; 4],
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Default, Clone, Copy, Debug, Eq, PartialEq)]
@@ -38,6 +52,28 @@ pub enum MarketStatus {
     Pause,
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Default, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MarkPriceType {
+    #[default]
+    Spot,
+    Ema,
+    Blended,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Default, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CircuitBreakerAction {
+    #[default]
+    Reject,
+    ReduceOnly,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MarkPriceUsage {
+    Trigger,
+    Liquidation,
+    Pnl,
+}
+
 impl Size for Market {
     const SIZE: usize = std::mem::size_of::<Market>() + 8;
 }
@@ -218,12 +254,101 @@ impl Market {
         self.funding_fee.update_last_update()
     }
 
+    //returns false when the price trips the circuit breaker, such a price is not folded into the
+    //ema so it stays the reference until a price within the deviation or an admin clears it
+    //callers executing against the price skip the execution on false instead of failing, so the
+    //tripped status and the deviated print count persist
+    pub fn update_mark_price(&mut self, price_data: &OraclePriceData) -> BumpResult<bool> {
+        let spot_price = price_data.price;
+        if self.is_price_deviation_exceeded(spot_price)? {
+            if !self.record_deviated_price(price_data.publish_time)? {
+                self.trip_price_circuit_breaker(spot_price);
+                return Ok(false);
+            }
+            //the move outlasted price_reanchor_count prints, it becomes the new reference
+            msg!("mark price re-anchored, index_price:{}", spot_price);
+            self.mark_price.ema_price = 0u128;
+        }
+        self.deviated_price_count = 0;
+        if self.price_circuit_breaker_tripped {
+            self.clear_price_circuit_breaker(spot_price);
+        }
+        let current_timestamp =
+            Clock::get().map_err(|_e| BumpErrorCode::TimestampNotFound)?.unix_timestamp;
+        self.mark_price.update(spot_price, self.ema_window_secs, current_timestamp)?;
+        Ok(true)
+    }
+
+    //counts deviated prints with distinct publish times, a reject mode market re-anchors once
+    //price_reanchor_count of them arrive in a row, a reduce only market waits for the admin
+    fn record_deviated_price(&mut self, publish_time: i64) -> BumpResult<bool> {
+        if self.price_reanchor_count == 0
+            || self.config.circuit_breaker_action != CircuitBreakerAction::Reject
+        {
+            return Ok(false);
+        }
+        if publish_time > self.mark_price.last_deviated_publish_time {
+            self.deviated_price_count = self.deviated_price_count.safe_add(1)?;
+            self.mark_price.last_deviated_publish_time = publish_time;
+        }
+        Ok(self.deviated_price_count >= self.price_reanchor_count)
+    }
+
+    pub fn is_price_deviation_exceeded(&self, spot_price: u128) -> BumpResult<bool> {
+        let reference_price = self.mark_price.ema_price;
+        if self.max_price_deviation_bps == 0 || reference_price == 0u128 {
+            return Ok(false);
+        }
+        let deviation_bps = spot_price
+            .abs_diff(reference_price)
+            .safe_mul(BPS_PRECISION)?
+            .safe_div(reference_price)?;
+        Ok(deviation_bps > self.max_price_deviation_bps.cast::<u128>()?)
+    }
+
+    fn trip_price_circuit_breaker(&mut self, spot_price: u128) {
+        msg!("price circuit breaker tripped, index_price:{}", spot_price);
+        if self.config.circuit_breaker_action == CircuitBreakerAction::ReduceOnly
+            && self.market_status == MarketStatus::NORMAL
+        {
+            self.market_status = MarketStatus::ReduceOnly;
+            self.price_circuit_breaker_tripped = true;
+            emit!(PriceCircuitBreakerEvent {
+                symbol: self.symbol,
+                index_price: spot_price,
+                reference_price: self.mark_price.ema_price,
+                tripped: true,
+                market_status: self.market_status,
+            });
+        }
+    }
+
+    fn clear_price_circuit_breaker(&mut self, spot_price: u128) {
+        //only lift the reduce only status set by the breaker itself
+        if self.market_status == MarketStatus::ReduceOnly {
+            self.market_status = MarketStatus::NORMAL;
+        }
+        self.price_circuit_breaker_tripped = false;
+        emit!(PriceCircuitBreakerEvent {
+            symbol: self.symbol,
+            index_price: spot_price,
+            reference_price: self.mark_price.ema_price,
+            tripped: false,
+            market_status: self.market_status,
+        });
+    }
+
+    pub fn get_mark_price(&self, spot_price: u128, usage: MarkPriceUsage) -> BumpResult<u128> {
+        self.mark_price.get_price(spot_price, self.config.get_mark_price_type(usage))
+    }
+
     pub fn get_market_un_pnl(&self, is_long: bool, oracle_map: &mut OracleMap) -> BumpResult<i128> {
         let position = if is_long { &self.long_open_interest } else { &self.short_open_interest };
-        let mark_price = oracle_map
+        let spot_price = oracle_map
             .get_price_data(&self.index_mint_oracle)
             .map_err(|_e| BumpErrorCode::OracleNotFound)?
             .price;
+        let mark_price = self.get_mark_price(spot_price, MarkPriceUsage::Pnl)?;
         if position.entry_price == 0u128 {
             return Ok(0i128);
         };
@@ -273,6 +398,49 @@ impl MarketPosition {
     }
 }
 
+#[bumpin_zero_copy_unsafe]
+pub struct MarkPrice {
+    pub ema_price: u128,
+    pub last_update_time: i64,
+    pub last_deviated_publish_time: i64,
+}
+
+impl MarkPrice {
+    //time weighted ema: a spot price observed after `elapsed` seconds moves the ema by
+    //elapsed / ema_window_secs of the distance, a gap longer than the window resets it
+    pub fn update(
+        &mut self,
+        spot_price: u128,
+        ema_window_secs: u32,
+        current_timestamp: i64,
+    ) -> BumpResult {
+        let elapsed = current_timestamp.safe_sub(self.last_update_time)?.max(0).cast::<u128>()?;
+        let window = ema_window_secs.cast::<u128>()?;
+        if self.ema_price == 0u128 || window == 0u128 || elapsed >= window {
+            self.ema_price = spot_price;
+        } else if elapsed > 0u128 {
+            self.ema_price = self
+                .ema_price
+                .safe_mul(window.safe_sub(elapsed)?)?
+                .safe_add(spot_price.safe_mul(elapsed)?)?
+                .safe_div(window)?;
+        }
+        self.last_update_time = current_timestamp;
+        Ok(())
+    }
+
+    pub fn get_price(&self, spot_price: u128, price_type: MarkPriceType) -> BumpResult<u128> {
+        if self.ema_price == 0u128 {
+            return Ok(spot_price);
+        }
+        match price_type {
+            MarkPriceType::Spot => Ok(spot_price),
+            MarkPriceType::Ema => Ok(self.ema_price),
+            MarkPriceType::Blended => spot_price.safe_add(self.ema_price)?.safe_div(2u128),
+        }
+    }
+}
+
 #[bumpin_zero_copy_unsafe]
 pub struct MarketConfig {
     pub tick_size: u128,
@@ -285,7 +453,20 @@ pub struct MarketConfig {
     pub maximum_leverage: u32,
     pub minimum_leverage: u32,
     pub max_pool_liquidity_share_rate: u32,
-    pub padding: [u8; 4],
+    pub trigger_price_type: MarkPriceType,
+    pub liquidation_price_type: MarkPriceType,
+    pub pnl_price_type: MarkPriceType,
+    pub circuit_breaker_action: CircuitBreakerAction,
+}
+
+impl MarketConfig {
+    pub fn get_mark_price_type(&self, usage: MarkPriceUsage) -> MarkPriceType {
+        match usage {
+            MarkPriceUsage::Trigger => self.trigger_price_type,
+            MarkPriceUsage::Liquidation => self.liquidation_price_type,
+            MarkPriceUsage::Pnl => self.pnl_price_type,
+        }
+    }
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Default, Copy)]
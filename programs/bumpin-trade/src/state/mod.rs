@@ -1,9 +1,12 @@
 pub mod bump_events;
 pub mod infrastructure;
+pub mod keeper_registry;
 pub mod market;
 pub mod market_map;
 pub mod oracle;
 pub mod oracle_map;
+pub mod order;
+pub mod order_map;
 pub mod pool;
 pub mod pool_map;
 pub mod rewards;
@@ -1,39 +1,373 @@
 use anchor_lang::prelude::*;
+use arrayref::array_ref;
 use pyth_sdk_solana::state::SolanaPriceAccount;
+use pyth_solana_receiver_sdk::price_update::{PriceUpdateV2, VerificationLevel};
 
-use crate::errors::BumpErrorCode::{InvalidOracle, PythOffline};
+use crate::errors::BumpErrorCode::{
+    InvalidOracle, OracleConfidenceTooWide, OracleFeedIdMismatch, OraclePriceToOld,
+    OracleVerificationLevelTooLow, PythOffline,
+};
 use crate::errors::{BumpErrorCode, BumpResult};
+use crate::ids::{
+    pyth_program, pyth_push_oracle_program, pyth_receiver_program, switchboard_on_demand_program,
+};
 use crate::math::casting::Cast;
-use crate::math::constants::PRICE_PRECISION;
+use crate::math::constants::{BPS_PRECISION, PRICE_PRECISION};
 use crate::math::safe_math::SafeMath;
+use crate::validate;
+use bumpin_trade_attribute::bumpin_zero_copy_unsafe;
 
 #[derive(Default, Clone, Copy, Debug)]
 pub struct OraclePriceData {
     pub price: u128,
     pub confidence: u128,
+    pub publish_time: i64,
 }
 
 impl OraclePriceData {
     pub fn default_usd() -> Self {
-        OraclePriceData { price: PRICE_PRECISION, confidence: 1 }
+        OraclePriceData { price: PRICE_PRECISION, confidence: 1, publish_time: 0 }
     }
 }
 
-pub fn get_oracle_price(price_oracle: &AccountInfo) -> BumpResult<OraclePriceData> {
-    get_pyth_price(price_oracle, 1)
+const DEFAULT_MAX_STALENESS_SECS: u32 = 180;
+
+//zero disables a limit; the liquidation limits fall back to the normal ones when zero
+#[bumpin_zero_copy_unsafe]
+pub struct OracleValidity {
+    pub max_staleness_secs: u32,
+    pub max_confidence_bps: u32,
+    pub liquidation_max_staleness_secs: u32,
+    pub liquidation_max_confidence_bps: u32,
+}
+
+impl OracleValidity {
+    pub fn get_max_staleness_secs(&self, is_liquidation: bool) -> u32 {
+        let max_staleness_secs = if self.max_staleness_secs == 0 {
+            DEFAULT_MAX_STALENESS_SECS
+        } else {
+            self.max_staleness_secs
+        };
+        if is_liquidation {
+            max_staleness_secs.max(self.liquidation_max_staleness_secs)
+        } else {
+            max_staleness_secs
+        }
+    }
+
+    pub fn get_max_confidence_bps(&self, is_liquidation: bool) -> u32 {
+        if is_liquidation
+            && self.max_confidence_bps != 0
+            && self.liquidation_max_confidence_bps != 0
+        {
+            self.max_confidence_bps.max(self.liquidation_max_confidence_bps)
+        } else {
+            self.max_confidence_bps
+        }
+    }
+
+    pub fn stricter(&self, other: &OracleValidity) -> OracleValidity {
+        let min_limit = |a: u32, b: u32| match (a, b) {
+            (0, b) => b,
+            (a, 0) => a,
+            (a, b) => a.min(b),
+        };
+        OracleValidity {
+            max_staleness_secs: self
+                .get_max_staleness_secs(false)
+                .min(other.get_max_staleness_secs(false)),
+            max_confidence_bps: min_limit(self.max_confidence_bps, other.max_confidence_bps),
+            liquidation_max_staleness_secs: min_limit(
+                self.liquidation_max_staleness_secs,
+                other.liquidation_max_staleness_secs,
+            ),
+            liquidation_max_confidence_bps: min_limit(
+                self.liquidation_max_confidence_bps,
+                other.liquidation_max_confidence_bps,
+            ),
+        }
+    }
+
+    pub fn validate_config(&self) -> BumpResult {
+        validate!(
+            self.max_confidence_bps.cast::<u128>()? <= BPS_PRECISION
+                && self.liquidation_max_confidence_bps.cast::<u128>()? <= BPS_PRECISION,
+            BumpErrorCode::InvalidParam
+        )?;
+        //liquidation thresholds can only be looser than the normal ones
+        validate!(
+            self.liquidation_max_staleness_secs == 0
+                || self.liquidation_max_staleness_secs >= self.get_max_staleness_secs(false),
+            BumpErrorCode::InvalidParam
+        )?;
+        validate!(
+            self.liquidation_max_confidence_bps == 0
+                || (self.max_confidence_bps != 0
+                    && self.liquidation_max_confidence_bps >= self.max_confidence_bps),
+            BumpErrorCode::InvalidParam
+        )?;
+        Ok(())
+    }
+
+    pub fn validate_price(
+        &self,
+        price_data: &OraclePriceData,
+        current_timestamp: i64,
+        is_liquidation: bool,
+    ) -> BumpResult {
+        let max_staleness_secs = self.get_max_staleness_secs(is_liquidation);
+        validate!(
+            price_data.publish_time.safe_add(max_staleness_secs.cast::<i64>()?)?
+                >= current_timestamp,
+            OraclePriceToOld
+        )?;
+
+        let max_confidence_bps = self.get_max_confidence_bps(is_liquidation);
+        if max_confidence_bps != 0 {
+            validate!(price_data.price > 0u128, InvalidOracle)?;
+            validate!(
+                price_data.confidence.safe_mul(BPS_PRECISION)?.safe_div(price_data.price)?
+                    <= max_confidence_bps.cast::<u128>()?,
+                OracleConfidenceTooWide
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Default, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OracleSource {
+    #[default]
+    Pyth,
+    PythPull,
+    SwitchboardOnDemand,
+}
+
+impl OracleSource {
+    pub fn from_owner(owner: &Pubkey) -> Option<OracleSource> {
+        if owner.eq(&pyth_program::id()) {
+            Some(OracleSource::Pyth)
+        } else if owner.eq(&pyth_receiver_program::id())
+            || owner.eq(&pyth_push_oracle_program::id())
+        {
+            Some(OracleSource::PythPull)
+        } else if owner.eq(&switchboard_on_demand_program::id()) {
+            Some(OracleSource::SwitchboardOnDemand)
+        } else {
+            None
+        }
+    }
+}
+
+pub const MAX_SECONDARY_ORACLES: usize = 2;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Default, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OracleAggregation {
+    #[default]
+    None,
+    //use the secondary oracles in order only when the ones before are unavailable or fail their
+    //validity
+    Fallback,
+    //median of the sources passing their validity, two sources left must agree within
+    //max_deviation_bps as there is no majority to outvote a bad one
+    Median,
+    //primary price, rejected when it deviates from any secondary one by more than max_deviation_bps
+    MaxDeviation,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Default, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OraclePriceSource {
+    #[default]
+    Primary,
+    Secondary,
+    Median,
+}
+
+#[bumpin_zero_copy_unsafe]
+pub struct OracleFeed {
+    pub oracle_key: Pubkey,
+    pub feed_id: [u8; 32],
+    pub oracle_source: OracleSource,
+    pub padding: [u8; 7],
+}
+
+impl OracleFeed {
+    pub fn is_set(&self) -> bool {
+        !self.oracle_key.eq(&Pubkey::default())
+    }
+}
+
+#[bumpin_zero_copy_unsafe]
+pub struct SecondaryOracle {
+    pub oracles: [OracleFeed; MAX_SECONDARY_ORACLES],
+    pub max_deviation_bps: u32,
+    pub aggregation: OracleAggregation,
+    pub padding: [u8; 11],
+}
+
+impl SecondaryOracle {
+    pub fn is_enabled(&self) -> bool {
+        self.aggregation != OracleAggregation::None
+    }
+
+    pub fn get_oracles(&self) -> impl Iterator<Item = &OracleFeed> {
+        self.oracles.iter().filter(|oracle| oracle.is_set())
+    }
+
+    pub fn validate_deviation(
+        &self,
+        reference_price: &OraclePriceData,
+        price: &OraclePriceData,
+    ) -> BumpResult {
+        if self.max_deviation_bps == 0 {
+            return Ok(());
+        }
+        validate!(reference_price.price > 0u128, InvalidOracle)?;
+        let deviation = reference_price.price.abs_diff(price.price);
+        validate!(
+            deviation.safe_mul(BPS_PRECISION)?.safe_div(reference_price.price)?
+                <= self.max_deviation_bps.cast::<u128>()?,
+            BumpErrorCode::OraclePriceDeviationTooLarge
+        )?;
+        Ok(())
+    }
+
+    //the middle price of an odd number of sources, the mean of the two middle ones otherwise
+    pub fn get_median_price(&self, prices: &mut [OraclePriceData]) -> BumpResult<OraclePriceData> {
+        validate!(!prices.is_empty(), InvalidOracle)?;
+        if prices.len() == 2 {
+            self.validate_deviation(&prices[0], &prices[1])?;
+        }
+        prices.sort_by_key(|price_data| price_data.price);
+        //both indexes point at the same price when the count is odd
+        let lower_price = prices[(prices.len() - 1) / 2].price;
+        let upper_price = prices[prices.len() / 2].price;
+        let price = lower_price.safe_add(upper_price)?.safe_div(2u128)?;
+        Ok(OraclePriceData {
+            price,
+            confidence: prices.iter().map(|price_data| price_data.confidence).max().unwrap_or(0),
+            publish_time: prices
+                .iter()
+                .map(|price_data| price_data.publish_time)
+                .min()
+                .unwrap_or(0),
+        })
+    }
+}
+
+pub fn get_oracle_price(
+    price_oracle: &AccountInfo,
+    oracle_source: OracleSource,
+    feed_id: Option<&[u8; 32]>,
+) -> BumpResult<OraclePriceData> {
+    match oracle_source {
+        OracleSource::Pyth => get_pyth_price(price_oracle, 1),
+        OracleSource::PythPull => get_pyth_pull_price(price_oracle, feed_id, 1),
+        OracleSource::SwitchboardOnDemand => get_switchboard_on_demand_price(price_oracle, feed_id),
+    }
+}
+
+//used when an oracle is configured: the account must belong to the program of its source and
+//yield a fresh, positive price for the configured feed
+//staleness and confidence are not checked by the readers below, see OracleValidity
+pub fn validate_oracle(
+    price_oracle: &AccountInfo,
+    oracle_source: OracleSource,
+    feed_id: &[u8; 32],
+) -> BumpResult {
+    validate!(
+        OracleSource::from_owner(price_oracle.owner) == Some(oracle_source),
+        BumpErrorCode::InvalidOracle
+    )?;
+    let price_data = get_oracle_price(price_oracle, oracle_source, Some(feed_id))?;
+    validate!(price_data.price > 0u128, BumpErrorCode::InvalidOracle)?;
+    let current_timestamp =
+        Clock::get().map_err(|_e| BumpErrorCode::TimestampNotFound)?.unix_timestamp;
+    OracleValidity::default().validate_price(&price_data, current_timestamp, false)
 }
 
 pub fn get_pyth_price(price_oracle: &AccountInfo, multiple: u128) -> BumpResult<OraclePriceData> {
     let price_feed =
         SolanaPriceAccount::account_info_to_feed(price_oracle).map_err(|_e| PythOffline)?;
-    let current_timestamp =
-        Clock::get().map_err(|_e| BumpErrorCode::TimestampNotFound)?.unix_timestamp;
-    let price_data =
-        price_feed.get_price_no_older_than(current_timestamp, 180).ok_or(PythOffline)?;
-    let oracle_price = price_data.price;
-    let oracle_conf = price_data.conf;
+    let price_data = price_feed.get_price_unchecked();
+    scale_oracle_price(
+        price_data.price,
+        price_data.conf,
+        price_data.expo,
+        price_data.publish_time,
+        multiple,
+    )
+}
+
+pub fn get_pyth_pull_price(
+    price_oracle: &AccountInfo,
+    feed_id: Option<&[u8; 32]>,
+    multiple: u128,
+) -> BumpResult<OraclePriceData> {
+    let data = price_oracle.try_borrow_data().map_err(|_e| InvalidOracle)?;
+    let price_update =
+        PriceUpdateV2::try_deserialize(&mut &data[..]).map_err(|_e| InvalidOracle)?;
+
+    //partially verified updates only carry a subset of the guardian signatures
+    validate!(
+        price_update.verification_level.gte(VerificationLevel::Full),
+        OracleVerificationLevelTooLow
+    )?;
+    let price_message = &price_update.price_message;
+    if let Some(feed_id) = feed_id {
+        validate!(price_message.feed_id.eq(feed_id), OracleFeedIdMismatch)?;
+    }
+    scale_oracle_price(
+        price_message.price,
+        price_message.conf,
+        price_message.exponent,
+        price_message.publish_time,
+        multiple,
+    )
+}
+
+//switchboard on-demand PullFeedAccountData is read by offset, values carry 18 decimals
+const SWITCHBOARD_FEED_ACCOUNT_SIZE: usize = 8 + 3200;
+const SWITCHBOARD_FEED_DISCRIMINATOR: [u8; 8] = [196, 27, 108, 196, 10, 215, 219, 40];
+const SWITCHBOARD_FEED_HASH_OFFSET: usize = 8 + 2112;
+const SWITCHBOARD_LAST_UPDATE_TIMESTAMP_OFFSET: usize = 8 + 2208;
+const SWITCHBOARD_RESULT_VALUE_OFFSET: usize = 8 + 2256;
+const SWITCHBOARD_RESULT_STD_DEV_OFFSET: usize = SWITCHBOARD_RESULT_VALUE_OFFSET + 16;
+const SWITCHBOARD_PRECISION: u128 = 10u128.pow(18);
+
+pub fn get_switchboard_on_demand_price(
+    price_oracle: &AccountInfo,
+    feed_id: Option<&[u8; 32]>,
+) -> BumpResult<OraclePriceData> {
+    let data = price_oracle.try_borrow_data().map_err(|_e| InvalidOracle)?;
+    validate!(data.len() >= SWITCHBOARD_FEED_ACCOUNT_SIZE, InvalidOracle)?;
+    validate!(array_ref![data, 0, 8] == &SWITCHBOARD_FEED_DISCRIMINATOR, InvalidOracle)?;
+    if let Some(feed_id) = feed_id {
+        validate!(
+            array_ref![data, SWITCHBOARD_FEED_HASH_OFFSET, 32] == feed_id,
+            OracleFeedIdMismatch
+        )?;
+    }
 
-    let oracle_precision = 10_u128.pow(price_data.expo.unsigned_abs());
+    let value = i128::from_le_bytes(*array_ref![data, SWITCHBOARD_RESULT_VALUE_OFFSET, 16]);
+    let std_dev = i128::from_le_bytes(*array_ref![data, SWITCHBOARD_RESULT_STD_DEV_OFFSET, 16]);
+    let publish_time =
+        i64::from_le_bytes(*array_ref![data, SWITCHBOARD_LAST_UPDATE_TIMESTAMP_OFFSET, 8]);
+
+    let oracle_scale_div = SWITCHBOARD_PRECISION.safe_div(PRICE_PRECISION)?;
+    let price = value.cast::<u128>().map_err(|_e| InvalidOracle)?.safe_div(oracle_scale_div)?;
+    let confidence = std_dev.unsigned_abs().safe_div(oracle_scale_div)?;
+    Ok(OraclePriceData { price, confidence, publish_time })
+}
+
+fn scale_oracle_price(
+    oracle_price: i64,
+    oracle_conf: u64,
+    oracle_expo: i32,
+    publish_time: i64,
+    multiple: u128,
+) -> BumpResult<OraclePriceData> {
+    let oracle_precision = 10_u128.pow(oracle_expo.unsigned_abs());
 
     if oracle_precision <= multiple {
         msg!("Multiple larger than oracle precision");
@@ -63,5 +397,100 @@ pub fn get_pyth_price(price_oracle: &AccountInfo, multiple: u128) -> BumpResult<
         .safe_div(oracle_scale_div)?
         .cast::<u128>()?;
 
-    Ok(OraclePriceData { price: oracle_price_scaled, confidence: oracle_conf_scaled })
+    Ok(OraclePriceData { price: oracle_price_scaled, confidence: oracle_conf_scaled, publish_time })
+}
+
+#[cfg(test)]
+pub mod test {
+    use anchor_lang::prelude::*;
+    use anchor_lang::solana_program::hash::hash;
+
+    use crate::ids::switchboard_on_demand_program;
+    use crate::state::oracle::{
+        get_switchboard_on_demand_price, SWITCHBOARD_FEED_ACCOUNT_SIZE,
+        SWITCHBOARD_FEED_DISCRIMINATOR, SWITCHBOARD_FEED_HASH_OFFSET,
+        SWITCHBOARD_LAST_UPDATE_TIMESTAMP_OFFSET, SWITCHBOARD_RESULT_STD_DEV_OFFSET,
+        SWITCHBOARD_RESULT_VALUE_OFFSET,
+    };
+
+    pub fn switchboard_feed_data(
+        feed_id: [u8; 32],
+        value: i128,
+        std_dev: i128,
+        timestamp: i64,
+    ) -> Vec<u8> {
+        let mut data = vec![0u8; SWITCHBOARD_FEED_ACCOUNT_SIZE];
+        data[..8].copy_from_slice(&SWITCHBOARD_FEED_DISCRIMINATOR);
+        data[SWITCHBOARD_FEED_HASH_OFFSET..SWITCHBOARD_FEED_HASH_OFFSET + 32]
+            .copy_from_slice(&feed_id);
+        data[SWITCHBOARD_LAST_UPDATE_TIMESTAMP_OFFSET
+            ..SWITCHBOARD_LAST_UPDATE_TIMESTAMP_OFFSET + 8]
+            .copy_from_slice(&timestamp.to_le_bytes());
+        data[SWITCHBOARD_RESULT_VALUE_OFFSET..SWITCHBOARD_RESULT_VALUE_OFFSET + 16]
+            .copy_from_slice(&value.to_le_bytes());
+        data[SWITCHBOARD_RESULT_STD_DEV_OFFSET..SWITCHBOARD_RESULT_STD_DEV_OFFSET + 16]
+            .copy_from_slice(&std_dev.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_switchboard_feed_discriminator() {
+        assert_eq!(
+            hash(b"account:PullFeedAccountData").to_bytes()[..8],
+            SWITCHBOARD_FEED_DISCRIMINATOR
+        );
+    }
+
+    #[test]
+    fn test_get_switchboard_on_demand_price() {
+        let key = Pubkey::new_unique();
+        let owner = switchboard_on_demand_program::id();
+        let feed_id = [7u8; 32];
+        let mut lamports = 0u64;
+        //65000.5 and 1.25 with 18 decimals
+        let mut data = switchboard_feed_data(
+            feed_id,
+            65_000_500_000_000_000_000_000,
+            1_250_000_000_000_000_000,
+            1_700_000_000,
+        );
+        let account_info =
+            AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+
+        let price_data = get_switchboard_on_demand_price(&account_info, Some(&feed_id)).unwrap();
+        assert_eq!(price_data.price, 6_500_050_000_000);
+        assert_eq!(price_data.confidence, 125_000_000);
+        assert_eq!(price_data.publish_time, 1_700_000_000);
+        assert!(get_switchboard_on_demand_price(&account_info, None).is_ok());
+        assert!(get_switchboard_on_demand_price(&account_info, Some(&[8u8; 32])).is_err());
+    }
+
+    #[test]
+    fn test_get_switchboard_on_demand_price_rejects_invalid_accounts() {
+        let key = Pubkey::new_unique();
+        let owner = switchboard_on_demand_program::id();
+        let feed_id = [7u8; 32];
+
+        //negative prices
+        let mut lamports = 0u64;
+        let mut data = switchboard_feed_data(feed_id, -1, 0, 0);
+        let account_info =
+            AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        assert!(get_switchboard_on_demand_price(&account_info, Some(&feed_id)).is_err());
+
+        //other accounts of the program
+        let mut lamports = 0u64;
+        let mut data = switchboard_feed_data(feed_id, 1, 0, 0);
+        data[0] = 0;
+        let account_info =
+            AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        assert!(get_switchboard_on_demand_price(&account_info, Some(&feed_id)).is_err());
+
+        let mut lamports = 0u64;
+        let mut data = switchboard_feed_data(feed_id, 1, 0, 0);
+        data.truncate(SWITCHBOARD_FEED_ACCOUNT_SIZE - 1);
+        let account_info =
+            AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        assert!(get_switchboard_on_demand_price(&account_info, Some(&feed_id)).is_err());
+    }
 }
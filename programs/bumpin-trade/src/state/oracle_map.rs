@@ -3,20 +3,36 @@ use std::collections::BTreeMap;
 use anchor_lang::prelude::*;
 use anchor_lang::Key;
 
-use crate::errors::BumpErrorCode::OracleNotFound;
+use crate::errors::BumpErrorCode::{OracleNotFound, TimestampNotFound};
 use crate::errors::BumpResult;
-use crate::ids::pyth_program;
 use crate::math::safe_unwrap::SafeUnwrap;
-use crate::state::oracle::{get_oracle_price, OraclePriceData};
+use crate::state::bump_events::OraclePriceAggregationEvent;
+use crate::state::market::{MarkPrice, MarkPriceUsage, Market, MarketConfig};
+use crate::state::oracle::{
+    get_oracle_price, OracleAggregation, OraclePriceData, OraclePriceSource, OracleSource,
+    OracleValidity, SecondaryOracle,
+};
+use crate::state::trade_token::TradeToken;
 
 pub struct AccountInfoAndOracleSource<'a> {
     /// CHECK: ownders are validated in OracleMap::load
     pub account_info: AccountInfo<'a>,
+    pub oracle_source: OracleSource,
+}
+
+struct MarketMarkPrice {
+    mark_price: MarkPrice,
+    config: MarketConfig,
 }
 
 pub struct OracleMap<'a> {
     oracles: BTreeMap<Pubkey, AccountInfoAndOracleSource<'a>>,
     price_data: BTreeMap<Pubkey, OraclePriceData>,
+    feed_ids: BTreeMap<Pubkey, [u8; 32]>,
+    validities: BTreeMap<Pubkey, OracleValidity>,
+    secondary_oracles: BTreeMap<Pubkey, SecondaryOracle>,
+    mark_prices: BTreeMap<[u8; 32], MarketMarkPrice>,
+    is_liquidation: bool,
 }
 
 impl<'a> OracleMap<'a> {
@@ -28,39 +44,398 @@ impl<'a> OracleMap<'a> {
         if self.price_data.contains_key(pubkey) {
             return self.price_data.get(pubkey).safe_unwrap().clone();
         }
-        let account_info = match self.oracles.get(pubkey) {
-            Some(AccountInfoAndOracleSource { account_info }) => account_info,
-            None => {
-                msg!("oracle pubkey not found in oracle_map: {}", pubkey);
-                return Err(OracleNotFound);
+        let price_result = match self.secondary_oracles.get(pubkey) {
+            Some(secondary_oracle) => {
+                self.get_aggregated_price(pubkey, &secondary_oracle.clone())?
             },
+            None => self.load_price(pubkey)?,
         };
-        let price_result = get_oracle_price(account_info)?;
         self.price_data.insert(*pubkey, price_result);
 
         self.price_data.get(pubkey).safe_unwrap()
     }
 
+    //the mark price of the registered market with this symbol, spot when there is none
+    //valuations read during a liquidation use the liquidation mark price instead of the pnl one
+    pub fn get_mark_price(
+        &mut self,
+        pubkey: &Pubkey,
+        symbol: &[u8; 32],
+        usage: MarkPriceUsage,
+    ) -> BumpResult<u128> {
+        let spot_price = self.get_price_data(pubkey)?.price;
+        let usage = if self.is_liquidation && usage == MarkPriceUsage::Pnl {
+            MarkPriceUsage::Liquidation
+        } else {
+            usage
+        };
+        match self.mark_prices.get(symbol) {
+            Some(MarketMarkPrice { mark_price, config }) => {
+                mark_price.get_price(spot_price, config.get_mark_price_type(usage))
+            },
+            None => Ok(spot_price),
+        }
+    }
+
+    fn get_aggregated_price(
+        &self,
+        pubkey: &Pubkey,
+        secondary_oracle: &SecondaryOracle,
+    ) -> BumpResult<OraclePriceData> {
+        //only a source that failed is worth an event, the healthy path stays quiet
+        let (price_data, price_source, price_oracle_key, is_degraded) = match secondary_oracle
+            .aggregation
+        {
+            OracleAggregation::Fallback => match self.load_price(pubkey) {
+                Ok(primary_price) => (primary_price, OraclePriceSource::Primary, *pubkey, false),
+                Err(e) => {
+                    msg!("primary oracle {} failed with {:?}, fall back to secondary", pubkey, e);
+                    let mut fallback_price = Err(e);
+                    for oracle in secondary_oracle.get_oracles() {
+                        fallback_price = self
+                            .load_price(&oracle.oracle_key)
+                            .map(|price_data| (price_data, oracle.oracle_key));
+                        if fallback_price.is_ok() {
+                            break;
+                        }
+                    }
+                    let (price_data, oracle_key) = fallback_price?;
+                    (price_data, OraclePriceSource::Secondary, oracle_key, true)
+                },
+            },
+            OracleAggregation::Median => {
+                let primary_price = self.load_price(pubkey);
+                let mut sources = Vec::new();
+                if let Ok(price_data) = &primary_price {
+                    sources.push((*price_data, OraclePriceSource::Primary, *pubkey));
+                }
+                let mut source_count = 1usize;
+                for oracle in secondary_oracle.get_oracles() {
+                    source_count += 1;
+                    if let Ok(price_data) = self.load_price(&oracle.oracle_key) {
+                        sources.push((price_data, OraclePriceSource::Secondary, oracle.oracle_key));
+                    }
+                }
+                let is_degraded = sources.len() < source_count;
+                match sources.len() {
+                    //every source failed, the primary error is the one reported
+                    0 => return primary_price,
+                    1 => (sources[0].0, sources[0].1, sources[0].2, is_degraded),
+                    _ => {
+                        let mut prices: Vec<OraclePriceData> =
+                            sources.iter().map(|source| source.0).collect();
+                        let median_price = secondary_oracle.get_median_price(&mut prices)?;
+                        (median_price, OraclePriceSource::Median, *pubkey, is_degraded)
+                    },
+                }
+            },
+            OracleAggregation::MaxDeviation => {
+                let primary_price = self.load_price(pubkey)?;
+                for oracle in secondary_oracle.get_oracles() {
+                    let secondary_price = self.load_price(&oracle.oracle_key)?;
+                    secondary_oracle.validate_deviation(&primary_price, &secondary_price)?;
+                }
+                (primary_price, OraclePriceSource::Primary, *pubkey, false)
+            },
+            OracleAggregation::None => {
+                (self.load_price(pubkey)?, OraclePriceSource::Primary, *pubkey, false)
+            },
+        };
+
+        if is_degraded {
+            emit!(OraclePriceAggregationEvent {
+                oracle_key: *pubkey,
+                price_oracle_key,
+                aggregation: secondary_oracle.aggregation,
+                price_source,
+                price: price_data.price,
+                confidence: price_data.confidence,
+            });
+        }
+        Ok(price_data)
+    }
+
+    fn load_price(&self, pubkey: &Pubkey) -> BumpResult<OraclePriceData> {
+        let (account_info, oracle_source) = match self.oracles.get(pubkey) {
+            Some(AccountInfoAndOracleSource { account_info, oracle_source }) => {
+                (account_info, *oracle_source)
+            },
+            None => {
+                msg!("oracle pubkey not found in oracle_map: {}", pubkey);
+                return Err(OracleNotFound);
+            },
+        };
+        let price_result =
+            get_oracle_price(account_info, oracle_source, self.feed_ids.get(pubkey))?;
+        let current_timestamp = Clock::get().map_err(|_e| TimestampNotFound)?.unix_timestamp;
+        self.validities.get(pubkey).copied().unwrap_or_default().validate_price(
+            &price_result,
+            current_timestamp,
+            self.is_liquidation,
+        )?;
+        Ok(price_result)
+    }
+
     pub fn load(remaining_accounts: &'a [AccountInfo<'a>]) -> BumpResult<OracleMap<'a>> {
         let mut oracles: BTreeMap<Pubkey, AccountInfoAndOracleSource<'a>> = BTreeMap::new();
 
         for account_info in remaining_accounts.iter() {
-            if account_info.owner == &pyth_program::id() {
+            if let Some(oracle_source) = OracleSource::from_owner(account_info.owner) {
                 let pubkey = account_info.key();
                 oracles.insert(
                     pubkey,
-                    AccountInfoAndOracleSource { account_info: account_info.clone() },
+                    AccountInfoAndOracleSource {
+                        account_info: account_info.clone(),
+                        oracle_source,
+                    },
                 );
             }
         }
 
-        Ok(OracleMap { oracles, price_data: BTreeMap::new() })
+        Ok(OracleMap {
+            oracles,
+            price_data: BTreeMap::new(),
+            feed_ids: BTreeMap::new(),
+            validities: BTreeMap::new(),
+            secondary_oracles: BTreeMap::new(),
+            mark_prices: BTreeMap::new(),
+            is_liquidation: false,
+        })
+    }
+
+    //prices are validated when first read, so this has to be called before any price is loaded
+    pub fn use_liquidation_thresholds(&mut self) {
+        self.is_liquidation = true;
+    }
+
+    //a pull oracle account can be re-posted with any feed, so prices read from it are checked
+    //against the feed id configured on the trade token or market that references it
+    pub fn register_trade_token(&mut self, trade_token: &TradeToken) {
+        self.register_oracle(
+            &trade_token.oracle_key,
+            trade_token.oracle_source,
+            trade_token.oracle_feed_id,
+            trade_token.oracle_validity,
+        );
+        self.register_secondary_oracle(
+            &trade_token.oracle_key,
+            trade_token.secondary_oracle,
+            trade_token.oracle_validity,
+        );
+    }
+
+    pub fn register_market(&mut self, market: &Market) {
+        self.register_oracle(
+            &market.index_mint_oracle,
+            market.index_oracle_source,
+            market.index_oracle_feed_id,
+            market.oracle_validity,
+        );
+        self.register_secondary_oracle(
+            &market.index_mint_oracle,
+            market.index_secondary_oracle,
+            market.oracle_validity,
+        );
+        //several markets can share one index oracle, each keeps its own mark price
+        self.mark_prices.insert(
+            market.symbol,
+            MarketMarkPrice { mark_price: market.mark_price, config: market.config },
+        );
+    }
+
+    //an oracle shared by a trade token and a market is checked with the stricter thresholds
+    fn register_oracle(
+        &mut self,
+        oracle: &Pubkey,
+        oracle_source: OracleSource,
+        feed_id: [u8; 32],
+        validity: OracleValidity,
+    ) {
+        if oracle_source != OracleSource::Pyth {
+            self.feed_ids.insert(*oracle, feed_id);
+        }
+        let validity = match self.validities.get(oracle) {
+            Some(registered) => registered.stricter(&validity),
+            None => validity,
+        };
+        self.validities.insert(*oracle, validity);
+    }
+
+    fn register_secondary_oracle(
+        &mut self,
+        oracle: &Pubkey,
+        secondary_oracle: SecondaryOracle,
+        validity: OracleValidity,
+    ) {
+        if !secondary_oracle.is_enabled() {
+            return;
+        }
+        for secondary in secondary_oracle.get_oracles() {
+            self.register_oracle(
+                &secondary.oracle_key,
+                secondary.oracle_source,
+                secondary.feed_id,
+                validity,
+            );
+        }
+        self.secondary_oracles.insert(*oracle, secondary_oracle);
     }
 }
 
 #[cfg(test)]
 impl<'a> OracleMap<'a> {
     pub fn empty() -> OracleMap<'a> {
-        OracleMap { oracles: BTreeMap::new(), price_data: BTreeMap::new() }
+        OracleMap {
+            oracles: BTreeMap::new(),
+            price_data: BTreeMap::new(),
+            feed_ids: BTreeMap::new(),
+            validities: BTreeMap::new(),
+            secondary_oracles: BTreeMap::new(),
+            mark_prices: BTreeMap::new(),
+            is_liquidation: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anchor_lang::prelude::*;
+    use anchor_lang::solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+
+    use crate::ids::switchboard_on_demand_program;
+    use crate::math::constants::PRICE_PRECISION;
+    use crate::state::oracle::test::switchboard_feed_data;
+    use crate::state::oracle::{
+        OracleAggregation, OracleFeed, OracleSource, OracleValidity, SecondaryOracle,
+    };
+    use crate::state::oracle_map::OracleMap;
+
+    const NOW: i64 = 1_700_000_000;
+    //older than the default staleness limit
+    const STALE: i64 = NOW - 1_000;
+
+    struct ClockStubs;
+
+    impl SyscallStubs for ClockStubs {
+        fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+            unsafe {
+                *(var_addr as *mut Clock) = Clock { unix_timestamp: NOW, ..Default::default() };
+            }
+            0
+        }
+    }
+
+    fn switchboard_account(price: u128, publish_time: i64) -> AccountInfo<'static> {
+        let data = switchboard_feed_data(
+            [0u8; 32],
+            (price * 10u128.pow(18) / PRICE_PRECISION) as i128,
+            0,
+            publish_time,
+        );
+        AccountInfo::new(
+            Box::leak(Box::new(Pubkey::new_unique())),
+            false,
+            false,
+            Box::leak(Box::new(0u64)),
+            Box::leak(data.into_boxed_slice()),
+            Box::leak(Box::new(switchboard_on_demand_program::id())),
+            false,
+            0,
+        )
+    }
+
+    /// Returns the map and the key of the primary oracle.
+    fn oracle_map(
+        primary: (u128, i64),
+        secondaries: &[(u128, i64)],
+        aggregation: OracleAggregation,
+        max_deviation_bps: u32,
+    ) -> (OracleMap<'static>, Pubkey) {
+        set_syscall_stubs(Box::new(ClockStubs));
+        let accounts: &'static [AccountInfo<'static>] = Box::leak(
+            std::iter::once(primary)
+                .chain(secondaries.iter().copied())
+                .map(|(price, publish_time)| switchboard_account(price, publish_time))
+                .collect::<Vec<_>>()
+                .into_boxed_slice(),
+        );
+        let mut oracle_map = OracleMap::load(accounts).unwrap();
+        let primary_key = *accounts[0].key;
+        let mut secondary_oracle =
+            SecondaryOracle { max_deviation_bps, aggregation, ..Default::default() };
+        for (index, account) in accounts[1..].iter().enumerate() {
+            secondary_oracle.oracles[index] = OracleFeed {
+                oracle_key: *account.key,
+                oracle_source: OracleSource::SwitchboardOnDemand,
+                ..Default::default()
+            };
+        }
+        oracle_map.register_secondary_oracle(
+            &primary_key,
+            secondary_oracle,
+            OracleValidity::default(),
+        );
+        (oracle_map, primary_key)
+    }
+
+    fn get_price(
+        primary: (u128, i64),
+        secondaries: &[(u128, i64)],
+        aggregation: OracleAggregation,
+        max_deviation_bps: u32,
+    ) -> Option<u128> {
+        let (mut oracle_map, primary_key) =
+            oracle_map(primary, secondaries, aggregation, max_deviation_bps);
+        oracle_map.get_price_data(&primary_key).ok().map(|price_data| price_data.price)
+    }
+
+    #[test]
+    fn test_aggregation_none() {
+        let aggregation = OracleAggregation::None;
+        assert_eq!(get_price((100, NOW), &[(200, NOW)], aggregation, 0), Some(100));
+        assert_eq!(get_price((100, STALE), &[(200, NOW)], aggregation, 0), None);
+    }
+
+    #[test]
+    fn test_aggregation_fallback() {
+        let aggregation = OracleAggregation::Fallback;
+        assert_eq!(get_price((100, NOW), &[(200, NOW)], aggregation, 0), Some(100));
+        assert_eq!(get_price((100, NOW), &[(200, STALE)], aggregation, 0), Some(100));
+        assert_eq!(get_price((100, STALE), &[(200, NOW)], aggregation, 0), Some(200));
+        assert_eq!(get_price((100, STALE), &[(200, STALE)], aggregation, 0), None);
+        //secondary oracles are tried in order
+        assert_eq!(get_price((100, STALE), &[(200, NOW), (300, NOW)], aggregation, 0), Some(200));
+        assert_eq!(get_price((100, STALE), &[(200, STALE), (300, NOW)], aggregation, 0), Some(300));
+    }
+
+    #[test]
+    fn test_aggregation_median() {
+        let aggregation = OracleAggregation::Median;
+        //the middle price outvotes a bad source
+        assert_eq!(get_price((100, NOW), &[(102, NOW), (500, NOW)], aggregation, 0), Some(102));
+        assert_eq!(get_price((100, NOW), &[(1, NOW), (101, NOW)], aggregation, 1_000), Some(100));
+        //without a majority two sources have to agree, their mean is used
+        assert_eq!(get_price((100, NOW), &[(110, NOW)], aggregation, 1_000), Some(105));
+        assert_eq!(get_price((100, NOW), &[(120, NOW)], aggregation, 1_000), None);
+        assert_eq!(
+            get_price((100, NOW), &[(110, NOW), (500, STALE)], aggregation, 1_000),
+            Some(105)
+        );
+        assert_eq!(get_price((100, STALE), &[(110, NOW), (500, NOW)], aggregation, 1_000), None);
+        //a single source left is used alone
+        assert_eq!(get_price((100, NOW), &[(120, STALE)], aggregation, 1_000), Some(100));
+        assert_eq!(get_price((100, STALE), &[(120, NOW)], aggregation, 1_000), Some(120));
+        assert_eq!(get_price((100, STALE), &[(120, STALE)], aggregation, 1_000), None);
+    }
+
+    #[test]
+    fn test_aggregation_max_deviation() {
+        let aggregation = OracleAggregation::MaxDeviation;
+        assert_eq!(get_price((100, NOW), &[(110, NOW)], aggregation, 1_000), Some(100));
+        assert_eq!(get_price((100, NOW), &[(120, NOW)], aggregation, 1_000), None);
+        assert_eq!(get_price((100, NOW), &[(110, NOW), (120, NOW)], aggregation, 1_000), None);
+        //the primary price can not be checked without the secondary one
+        assert_eq!(get_price((100, NOW), &[(110, STALE)], aggregation, 1_000), None);
+        assert_eq!(get_price((100, STALE), &[(110, NOW)], aggregation, 1_000), None);
     }
 }
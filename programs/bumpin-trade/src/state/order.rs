@@ -0,0 +1,21 @@
+use anchor_lang::prelude::*;
+
+use crate::state::infrastructure::user_order::UserOrder;
+use crate::traits::Size;
+
+/// A pending order of a user, stored in its own PDA seeded by
+/// `[b"order", user, order_id]` so that a user is not limited in the number of open orders.
+/// The account is closed with the rent refunded to the user authority once the order is
+/// executed or canceled. Keepers discover open orders with `getProgramAccounts`, filtering
+/// on the discriminator and `user_key`.
+#[account(zero_copy(unsafe))]
+#[derive(Default, Eq, PartialEq, Debug)]
+#[repr(C)]
+pub struct Order {
+    pub user_key: Pubkey,
+    pub order: UserOrder,
+}
+
+impl Size for Order {
+    const SIZE: usize = std::mem::size_of::<Order>() + 8;
+}
@@ -0,0 +1,267 @@
+use std::cell::RefMut;
+use std::collections::BTreeMap;
+
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use arrayref::array_ref;
+
+use crate::errors::BumpErrorCode::{CouldNotFindUserOrder, CouldNotLoadOrderData};
+use crate::errors::{BumpErrorCode, BumpResult};
+use crate::math::casting::Cast;
+use crate::state::bump_events::AddOrDeleteUserOrderEvent;
+use crate::state::infrastructure::user_order::{
+    OrderSide, OrderStatus, OrderType, PositionSide, StopType, UserOrder,
+};
+use crate::state::order::Order;
+use crate::state::user::User;
+use crate::traits::Size;
+use crate::validate;
+
+/// Order accounts of one user passed in the remaining accounts, keyed by order id.
+/// Closed orders refund their rent to the user authority, which must be passed writable
+/// in the remaining accounts whenever an order may be closed.
+pub struct OrderMap<'a> {
+    pub orders: BTreeMap<u64, AccountLoader<'a, Order>>,
+    pub authority: Option<&'a AccountInfo<'a>>,
+    /// Stored order being executed, passed in its own account instead of the remaining accounts.
+    pub executing_order_id: Option<u64>,
+}
+
+impl<'a> OrderMap<'a> {
+    #[track_caller]
+    #[inline(always)]
+    pub fn get_mut_ref(&self, order_id: u64) -> BumpResult<RefMut<'_, Order>> {
+        let loader = match self.orders.get(&order_id) {
+            None => {
+                return Err(CouldNotFindUserOrder);
+            },
+            Some(loader) => loader,
+        };
+        match loader.load_mut() {
+            Ok(order) => Ok(order),
+            Err(e) => {
+                msg!("{:?}", e);
+                Err(CouldNotLoadOrderData)
+            },
+        }
+    }
+
+    #[track_caller]
+    pub fn get_all_order(&self) -> BumpResult<Vec<UserOrder>> {
+        let mut orders = Vec::new();
+        for loader in self.orders.values() {
+            let order = loader.load().map_err(|_e| CouldNotLoadOrderData)?;
+            if !order.order.status.eq(&OrderStatus::INIT) {
+                orders.push(order.order);
+            }
+        }
+        Ok(orders)
+    }
+
+    /// Paths closing orders on behalf of another one (a liquidation, a canceled parent) need
+    /// every open order of the user, `loaded_order_id` is an order passed in its own account.
+    pub fn validate_all_orders_loaded(
+        &self,
+        user: &User,
+        loaded_order_id: Option<u64>,
+    ) -> BumpResult {
+        let mut loaded_count = self.orders.len();
+        if let Some(order_id) = loaded_order_id {
+            if !self.orders.contains_key(&order_id) {
+                loaded_count += 1;
+            }
+        }
+        validate!(
+            loaded_count == user.open_order_count.cast::<usize>()?,
+            BumpErrorCode::OrderAccountsMissing
+        )?;
+        Ok(())
+    }
+
+    #[track_caller]
+    pub fn close_order(&mut self, user: &mut User, order_id: u64) -> BumpResult {
+        let loader = self.orders.remove(&order_id).ok_or(CouldNotFindUserOrder)?;
+        let authority = self.authority.ok_or(BumpErrorCode::OrderRentReceiverNotFound)?;
+        let (user_key, order) = {
+            let order = loader.load().map_err(|_e| CouldNotLoadOrderData)?;
+            (order.user_key, order.order)
+        };
+        loader.close(authority.clone()).map_err(|_e| CouldNotLoadOrderData)?;
+        user.remove_open_order()?;
+        emit!(AddOrDeleteUserOrderEvent { user_key, order, is_add: false });
+        Ok(())
+    }
+
+    /// Cancels the stop orders of a decreased position, in hedge mode `position_is_long` keeps
+    /// the orders of the other side.
+    pub fn cancel_stop_orders(
+        &mut self,
+        user: &mut User,
+        order_id: u64,
+        symbol: [u8; 32],
+        margin_token: &Pubkey,
+        is_portfolio_margin: bool,
+        position_is_long: Option<bool>,
+    ) -> BumpResult<()> {
+        for user_order in self.get_all_order()? {
+            if user_order.order_id == order_id || !user_order.status.eq(&OrderStatus::USING) {
+                continue;
+            }
+            if user_order.symbol == symbol
+                && user_order.margin_mint_key.eq(margin_token)
+                && (user_order.order_type.eq(&OrderType::STOP)
+                    || user_order.order_type.eq(&OrderType::TRAILING_STOP))
+                && user_order.is_portfolio_margin == is_portfolio_margin
+                && position_is_long.unwrap_or(user_order.is_long_position())
+                    == user_order.is_long_position()
+            {
+                self.close_order(user, user_order.order_id)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn cancel_all_cross_orders(&mut self, user: &mut User) -> BumpResult<()> {
+        self.cancel_cross_orders(user, &None, &None)
+    }
+
+    /// Cancels the cross orders matching the filters, pending bracket orders go with their
+    /// increase order.
+    pub fn cancel_cross_orders(
+        &mut self,
+        user: &mut User,
+        symbol: &Option<[u8; 32]>,
+        order_side: &Option<OrderSide>,
+    ) -> BumpResult<()> {
+        for user_order in self.get_all_order()? {
+            //already closed along with its parent
+            if !self.orders.contains_key(&user_order.order_id) {
+                continue;
+            }
+            if !user_order.is_portfolio_margin || !user_order.is_matched(symbol, order_side) {
+                continue;
+            }
+            if user_order.position_side.eq(&PositionSide::INCREASE) {
+                user.sub_order_hold_in_usd(user_order.order_margin)?;
+            }
+            self.close_order(user, user_order.order_id)?;
+            self.cancel_bracket_orders(user, user_order.order_id)?;
+        }
+        Ok(())
+    }
+
+    /// Bracket orders become executable once their parent increase order is executed, they
+    /// close the size the parent added to the position.
+    pub fn activate_bracket_orders(&self, parent: &UserOrder, order_size: u128) -> BumpResult {
+        for (stop_type, trigger_price) in [
+            (StopType::TakeProfit, parent.take_profit_price),
+            (StopType::StopLoss, parent.stop_loss_price),
+        ] {
+            if trigger_price == 0u128 {
+                continue;
+            }
+            let mut order = self.get_mut_ref(parent.get_bracket_order_id(stop_type)?)?;
+            let bracket_order = &mut order.order;
+            validate!(
+                bracket_order.is_bracket
+                    && bracket_order.parent_order_id == parent.order_id
+                    && bracket_order.status.eq(&OrderStatus::PENDING),
+                BumpErrorCode::InvalidParam
+            )?;
+            bracket_order.status = OrderStatus::USING;
+            bracket_order.order_size = order_size;
+        }
+        Ok(())
+    }
+
+    pub fn cancel_bracket_orders(&mut self, user: &mut User, parent_order_id: u64) -> BumpResult {
+        for user_order in self.get_all_order()? {
+            if user_order.is_bracket
+                && user_order.parent_order_id == parent_order_id
+                && user_order.status.eq(&OrderStatus::PENDING)
+            {
+                self.close_order(user, user_order.order_id)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn cancel_linked_order(&mut self, user: &mut User, order: &UserOrder) -> BumpResult {
+        if let Some(linked_order_id) = order.get_linked_order_id()? {
+            if self.orders.contains_key(&linked_order_id) {
+                self.close_order(user, linked_order_id)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn update_all_orders_leverage(
+        &self,
+        leverage: u32,
+        symbol: [u8; 32],
+        margin_token: &Pubkey,
+        is_long: bool,
+        is_portfolio_margin: bool,
+    ) -> BumpResult {
+        for order_id in self.orders.keys() {
+            let mut order = self.get_mut_ref(*order_id)?;
+            let user_order = &mut order.order;
+            if user_order.status.eq(&OrderStatus::INIT) {
+                continue;
+            }
+            let is_long_order = user_order.order_side.eq(&OrderSide::LONG);
+            if user_order.is_portfolio_margin == is_portfolio_margin
+                && user_order.symbol == symbol
+                && user_order.margin_mint_key.eq(margin_token)
+                && ((is_long_order == is_long
+                    && user_order.position_side.eq(&PositionSide::INCREASE))
+                    || (is_long_order != user_order.position_side.eq(&PositionSide::DECREASE)))
+            {
+                user_order.set_leverage(leverage)
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> OrderMap<'a> {
+    pub fn load(
+        remaining_accounts: &'a [AccountInfo<'a>],
+        user: &User,
+    ) -> BumpResult<OrderMap<'a>> {
+        let mut order_map =
+            OrderMap { orders: BTreeMap::new(), authority: None, executing_order_id: None };
+        let order_discriminator: [u8; 8] = Order::discriminator();
+        for account_info in remaining_accounts {
+            if account_info.key.eq(&user.authority) && account_info.is_writable {
+                order_map.authority = Some(account_info);
+                continue;
+            }
+            if !account_info.owner.eq(&crate::id()) {
+                continue;
+            }
+            if let Ok(data) = account_info.try_borrow_data() {
+                if data.len() < Order::SIZE {
+                    continue;
+                }
+                let account_discriminator = array_ref![data, 0, 8];
+                if account_discriminator != &order_discriminator {
+                    continue;
+                }
+                //orders of other users are ignored
+                if !array_ref![data, 8, 32].eq(&user.key.to_bytes()) {
+                    continue;
+                }
+            } else {
+                continue;
+            }
+
+            let account_loader: AccountLoader<'a, Order> =
+                AccountLoader::try_from(account_info).or(Err(CouldNotLoadOrderData))?;
+            let order_id =
+                account_loader.load().map_err(|_e| CouldNotLoadOrderData)?.order.order_id;
+            order_map.orders.insert(order_id, account_loader);
+        }
+        Ok(order_map)
+    }
+}
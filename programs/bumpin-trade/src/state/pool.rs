@@ -191,6 +191,13 @@ impl Pool {
         Ok(())
     }
 
+    pub fn sub_insurance_fund(&mut self, amount: u128) -> BumpResult<()> {
+        let pre_pool = *self;
+        self.insurance_fund_amount = sub_u128(self.insurance_fund_amount, amount)?;
+        self.emit_pool_update_event(&pre_pool);
+        Ok(())
+    }
+
     pub fn add_stable_amount(&mut self, amount: u128) -> BumpResult<()> {
         let pre_pool = self.clone();
         self.stable_balance.amount = add_u128(self.stable_balance.amount, amount)?;
@@ -1,5 +1,7 @@
 use anchor_lang::prelude::*;
 
+use crate::errors::{BumpErrorCode, BumpResult};
+
 #[account]
 #[derive(Default)]
 #[repr(C)]
@@ -21,6 +23,47 @@ pub struct State {
     pub pool_sequence: u16,
     pub trade_token_sequence: u16,
     pub bump_signer_nonce: u8,
-    pub padding: [u8; 5],
-    pub reserve_padding: [u8; 32],
+    pub paused_operations: u8,
+    pub padding: [u8; 4],
+    pub pending_admin: Pubkey,
+    //fields below were appended after the first release, see migrate_state
+    pub liquidator_reward_ratio: u32,
+    pub execution_fee: u64,
+    pub liquidation_buffer_rate: u32,
+    pub hedge_mm_offset_rate: u32,
+}
+
+/// Admin, account creation and order cancel handlers are never paused so users can always leave,
+/// neither are the keeper cranks that only move funds between program vaults (rebalance, collect
+/// rewards) or refresh prices (update_mark_price, update_trailing_stop).
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PauseOperation {
+    Deposit = 0b0000001,
+    Withdraw = 0b0000010,
+    PlaceOrder = 0b0000100,
+    ExecuteOrder = 0b0001000,
+    Stake = 0b0010000,
+    UnStake = 0b0100000,
+    Liquidation = 0b1000000,
+}
+
+impl State {
+    pub fn is_operation_paused(&self, operation: PauseOperation) -> bool {
+        self.paused_operations & (operation as u8) != 0
+    }
+
+    pub fn validate_operation_not_paused(&self, operation: PauseOperation) -> BumpResult {
+        if !self.is_operation_paused(operation) {
+            return Ok(());
+        }
+        match operation {
+            PauseOperation::Deposit => Err(BumpErrorCode::DepositPaused),
+            PauseOperation::Withdraw => Err(BumpErrorCode::WithdrawPaused),
+            PauseOperation::PlaceOrder => Err(BumpErrorCode::PlaceOrderPaused),
+            PauseOperation::ExecuteOrder => Err(BumpErrorCode::ExecuteOrderPaused),
+            PauseOperation::Stake => Err(BumpErrorCode::StakeGloballyPaused),
+            PauseOperation::UnStake => Err(BumpErrorCode::UnStakeGloballyPaused),
+            PauseOperation::Liquidation => Err(BumpErrorCode::LiquidationPaused),
+        }
+    }
 }
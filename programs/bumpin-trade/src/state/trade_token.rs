@@ -2,6 +2,7 @@ use anchor_lang::prelude::*;
 
 use crate::errors::{BumpErrorCode, BumpResult};
 use crate::math::safe_math::SafeMath;
+use crate::state::oracle::{OracleSource, OracleValidity, SecondaryOracle};
 use crate::traits::{MarketIndexOffset, Size};
 use crate::validate;
 
@@ -19,8 +20,13 @@ pub struct TradeToken {
     pub liquidation_factor: u32, // 10^5
     pub index: u16,
     pub decimals: u16,
-    pub padding: [u8; 4],
-    pub reserve_padding: [u8; 32],
+    pub oracle_source: OracleSource,
+    pub padding: [u8; 3],
+    pub oracle_validity: OracleValidity,
+    pub reserve_padding: [u8; 16],
+    //fields below were appended after the first release, see migrate_trade_token
+    pub oracle_feed_id: [u8; 32],
+    pub secondary_oracle: SecondaryOracle,
 }
 
 impl Size for TradeToken {
@@ -2,7 +2,7 @@ use anchor_lang::prelude::*;
 use anchor_spl::token::{Token, TokenAccount};
 
 use crate::errors::BumpErrorCode::{
-    CouldNotFindUserOrder, CouldNotFindUserPosition, CouldNotFindUserStake, CouldNotFindUserToken,
+    CouldNotFindUserPosition, CouldNotFindUserStake, CouldNotFindUserToken,
 };
 use crate::errors::{BumpErrorCode, BumpResult};
 use crate::instructions::calculator;
@@ -12,12 +12,11 @@ use crate::math::safe_math::SafeMath;
 use crate::state::bump_events::{
     AddOrDeleteUserOrderEvent, UserHoldUpdateEvent, UserTokenBalanceUpdateEvent,
 };
-use crate::state::infrastructure::user_order::{
-    OrderSide, OrderStatus, OrderType, PositionSide, UserOrder,
-};
+use crate::state::infrastructure::user_order::{LegacyUserOrder, PositionSide, UserOrder};
 use crate::state::infrastructure::user_position::{PositionStatus, UserPosition};
 use crate::state::infrastructure::user_stake::{UserStake, UserStakeStatus};
 use crate::state::infrastructure::user_token::{UserToken, UserTokenStatus};
+use crate::state::market::MarkPriceUsage;
 use crate::state::oracle_map::OracleMap;
 use crate::state::state::State;
 use crate::state::trade_token::TradeToken;
@@ -37,11 +36,13 @@ pub struct User {
     pub tokens: [UserToken; 10],
     pub stakes: [UserStake; 10],
     pub positions: [UserPosition; 10],
-    pub orders: [UserOrder; 8],
+    pub legacy_orders: [LegacyUserOrder; 8],
     pub authority: Pubkey,
     pub created_at: i64,
     pub user_status: UserStatus,
-    pub padding: [u8; 7],
+    pub hedge_mode: bool,
+    pub padding: [u8; 2],
+    pub open_order_count: u32,
 }
 
 impl Default for User {
@@ -53,12 +54,14 @@ impl Default for User {
             tokens: [UserToken::default(); 10],
             stakes: [UserStake::default(); 10],
             positions: [UserPosition::default(); 10],
-            orders: [UserOrder::default(); 8],
+            legacy_orders: [LegacyUserOrder::default(); 8],
             key: Pubkey::default(),
             authority: Pubkey::default(),
             created_at: calculator::current_time(),
             user_status: UserStatus::NORMAL,
-            padding: [0u8; 7],
+            hedge_mode: false,
+            padding: [0u8; 2],
+            open_order_count: 0,
         }
     }
 }
@@ -179,27 +182,6 @@ impl User {
             .ok_or(CouldNotFindUserStake)
     }
 
-    pub fn get_user_order_ref(&self, order_id: u64) -> BumpResult<&UserOrder> {
-        self.get_user_order_index(order_id).map(|user_order| &self.orders[user_order])
-    }
-
-    pub fn add_user_order(&mut self, user_order: &UserOrder) -> BumpResult<usize> {
-        let new_user_order_index = self.next_usable_order_index()?;
-
-        let new_user_order = *user_order;
-        self.orders[new_user_order_index] = new_user_order;
-        Ok(new_user_order_index)
-    }
-
-    pub fn get_user_order_index(&self, order_id: u64) -> BumpResult<usize> {
-        self.orders
-            .iter()
-            .position(|user_order| {
-                user_order.status.eq(&OrderStatus::USING) && user_order.order_id == order_id
-            })
-            .ok_or(CouldNotFindUserOrder)
-    }
-
     pub fn get_user_position_mut_ref(
         &mut self,
         position_key: &Pubkey,
@@ -213,6 +195,34 @@ impl User {
             .map(|user_position| &self.positions[user_position])
     }
 
+    pub fn get_position_key(
+        &self,
+        symbol: [u8; 32],
+        is_portfolio_margin: bool,
+        is_long: bool,
+        program_id: &Pubkey,
+    ) -> BumpResult<Pubkey> {
+        if self.hedge_mode {
+            pda::generate_hedge_position_key(
+                &self.key,
+                symbol,
+                is_portfolio_margin,
+                is_long,
+                program_id,
+            )
+        } else {
+            pda::generate_position_key(&self.key, symbol, is_portfolio_margin, program_id)
+        }
+    }
+
+    pub fn has_open_position(&self) -> bool {
+        self.positions.iter().any(|position| position.status.eq(&PositionStatus::USING))
+    }
+
+    pub fn get_position_size(&self, position_key: &Pubkey) -> u128 {
+        self.get_user_position_ref(position_key).map_or(0u128, |position| position.position_size)
+    }
+
     pub fn add_user_position(&mut self, position_key: &Pubkey) -> BumpResult<usize> {
         let new_user_position_index = self.next_usable_position_index()?;
 
@@ -251,6 +261,37 @@ impl User {
         Ok(())
     }
 
+    pub fn add_open_order(&mut self) -> BumpResult {
+        self.open_order_count = self.open_order_count.safe_add(1)?;
+        Ok(())
+    }
+
+    pub fn remove_open_order(&mut self) -> BumpResult {
+        self.open_order_count = self.open_order_count.safe_sub(1)?;
+        Ok(())
+    }
+
+    /// Drops the cross orders left in the legacy order array and releases their hold, they have
+    /// no order account to pass so a liquidation can not be blocked by them.
+    pub fn release_legacy_cross_orders(&mut self) -> BumpResult {
+        for index in 0..self.legacy_orders.len() {
+            let legacy_order = self.legacy_orders[index];
+            if !legacy_order.is_live() || !legacy_order.is_portfolio_margin {
+                continue;
+            }
+            if legacy_order.position_side.eq(&PositionSide::INCREASE) {
+                self.sub_order_hold_in_usd(legacy_order.order_margin)?;
+            }
+            self.legacy_orders[index] = LegacyUserOrder::default();
+            emit!(AddOrDeleteUserOrderEvent {
+                user_key: self.key,
+                order: legacy_order.to_user_order(),
+                is_add: false
+            });
+        }
+        Ok(())
+    }
+
     pub fn sub_order_hold_in_usd(&mut self, amount: u128) -> BumpResult<()> {
         validate!(self.hold >= amount, BumpErrorCode::OrderHoldUsdSmallThanHoldUsd)?;
         let pre_hold = self.hold;
@@ -311,8 +352,9 @@ impl User {
         is_long: bool,
         program_id: &Pubkey,
     ) -> BumpResult<bool> {
+        //in hedge mode the key already tells the sides apart
         let position_key =
-            pda::generate_position_key(&self.key, symbol, is_portfolio_margin, program_id)?;
+            self.get_position_key(symbol, is_portfolio_margin, is_long, program_id)?;
         for position in &self.positions {
             if position.status.eq(&PositionStatus::INIT) {
                 continue;
@@ -326,15 +368,6 @@ impl User {
         Ok(true)
     }
 
-    pub fn next_usable_order_index(&self) -> BumpResult<usize> {
-        for (index, order) in self.orders.iter().enumerate() {
-            if order.status.eq(&OrderStatus::INIT) {
-                return Ok(index);
-            }
-        }
-        Err(BumpErrorCode::NoMoreOrderSpace)
-    }
-
     pub fn next_usable_user_token_index(&self) -> BumpResult<usize> {
         for (index, user_token) in self.tokens.iter().enumerate() {
             if user_token.user_token_status.eq(&UserTokenStatus::INIT) {
@@ -362,58 +395,12 @@ impl User {
         Err(BumpErrorCode::AmountNotEnough)
     }
 
-    pub fn add_order(&mut self, order: &UserOrder, index: usize) -> BumpResult {
-        self.orders[index] = *order;
-        emit!(AddOrDeleteUserOrderEvent { user_key: self.key, order: *order, is_add: true });
-        Ok(())
-    }
-
-    pub fn delete_order(&mut self, order_id: u64) -> BumpResult {
-        match self.get_user_order_index(order_id) {
-            Ok(order_index) => {
-                let user_key = self.key;
-                let order = self.orders[order_index];
-                self.orders[order_index] = UserOrder::default();
-                emit!(AddOrDeleteUserOrderEvent { user_key, order, is_add: false });
-            },
-            Err(_e) => {
-                //order not exist, do nothing
-            },
-        }
-        Ok(())
-    }
-
     pub fn delete_user_stake(&mut self, pool_key: &Pubkey) -> BumpResult {
         let index = self.get_user_stake_index(pool_key)?;
         self.stakes[index] = UserStake::default();
         Ok(())
     }
 
-    pub fn cancel_stop_orders(
-        &mut self,
-        order_id: u64,
-        symbol: [u8; 32],
-        margin_token: &Pubkey,
-        is_portfolio_margin: bool,
-    ) -> BumpResult<()> {
-        for user_order in self.orders {
-            if user_order.status.eq(&OrderStatus::INIT) {
-                continue;
-            }
-            if user_order.order_id == order_id {
-                continue;
-            }
-            if user_order.symbol == symbol
-                && user_order.margin_mint_key.eq(margin_token)
-                && user_order.order_type.eq(&OrderType::STOP)
-                && user_order.is_portfolio_margin == is_portfolio_margin
-            {
-                self.delete_order(user_order.order_id)?;
-            }
-        }
-        Ok(())
-    }
-
     pub fn delete_position(&mut self, position_key: &Pubkey) -> BumpResult {
         let position_index = self
             .positions
@@ -423,51 +410,6 @@ impl User {
         self.positions[position_index] = UserPosition::default();
         Ok(())
     }
-    pub fn get_order_leverage(
-        &self,
-        symbol: [u8; 32],
-        order_side: OrderSide,
-        is_portfolio_margin: bool,
-        leverage: u32,
-    ) -> BumpResult<u32> {
-        for order in self.orders {
-            if order.symbol == symbol
-                && order.order_side.eq(&order_side)
-                && order.position_side.eq(&PositionSide::DECREASE)
-                && order.is_portfolio_margin == is_portfolio_margin
-            {
-                return Ok(order.leverage);
-            }
-        }
-        Ok(leverage)
-    }
-
-    pub fn update_all_orders_leverage(
-        &mut self,
-        leverage: u32,
-        symbol: [u8; 32],
-        margin_token: &Pubkey,
-        is_long: bool,
-        is_portfolio_margin: bool,
-    ) -> BumpResult {
-        for user_order in &mut self.orders {
-            if user_order.status.eq(&OrderStatus::INIT) {
-                continue;
-            }
-            let is_long_order = user_order.order_side.eq(&OrderSide::LONG);
-            if user_order.is_portfolio_margin == is_portfolio_margin
-                && user_order.symbol == symbol
-                && user_order.margin_mint_key.eq(margin_token)
-                && ((is_long_order == is_long
-                    && user_order.position_side.eq(&PositionSide::INCREASE))
-                    || (is_long_order != user_order.position_side.eq(&PositionSide::DECREASE)))
-            {
-                user_order.set_leverage(leverage)
-            }
-        }
-        Ok(())
-    }
-
     pub fn update_all_position_from_portfolio_margin(
         &mut self,
         change_token_amount: i128,
@@ -529,15 +471,6 @@ impl User {
         Ok(())
     }
 
-    pub fn cancel_user_order(&mut self, user_order_index: usize) -> BumpResult {
-        let user_order = self.orders[user_order_index];
-        if user_order.is_portfolio_margin {
-            self.sub_order_hold_in_usd(user_order.order_margin)?;
-        }
-        self.orders[user_order_index] = UserOrder::default();
-        Ok(())
-    }
-
     pub fn sub_user_token_amount(&mut self, mint: &Pubkey, mut amount: u128) -> BumpResult {
         for user_position in &mut self.positions {
             if user_position.status.eq(&PositionStatus::INIT) {
@@ -746,7 +679,11 @@ impl User {
             }
 
             total_im_usd = total_im_usd.safe_add(user_position.initial_margin_usd)?;
-            let index_price = price_map.get_price_data(&user_position.index_mint_oracle)?.price;
+            let index_price = price_map.get_mark_price(
+                &user_position.index_mint_oracle,
+                &user_position.symbol,
+                MarkPriceUsage::Pnl,
+            )?;
             let trade_token =
                 trade_token_map.get_trade_token_by_mint_ref(&user_position.margin_mint_key)?;
             let position_un_pnl_usd = user_position.get_position_un_pnl_usd(index_price)?;
@@ -773,6 +710,36 @@ impl User {
         ))
     }
 
+    /// The long and the short cross positions of a symbol in hedge mode offset each other, the
+    /// maintenance margin of the smaller leg is waived by `hedge_mm_offset_rate`. Returns the
+    /// waived maintenance margin.
+    pub fn get_hedged_position_mm(&self, hedge_mm_offset_rate: u32) -> BumpResult<u128> {
+        let mut hedged_position_mm = 0u128;
+        if !self.hedge_mode {
+            return Ok(hedged_position_mm);
+        }
+        for long_position in &self.positions {
+            if long_position.status.eq(&PositionStatus::INIT)
+                || !long_position.is_portfolio_margin
+                || !long_position.is_long
+            {
+                continue;
+            }
+            if let Some(short_position) = self.positions.iter().find(|short_position| {
+                short_position.status.eq(&PositionStatus::USING)
+                    && short_position.is_portfolio_margin
+                    && !short_position.is_long
+                    && short_position.symbol == long_position.symbol
+            }) {
+                hedged_position_mm = hedged_position_mm.safe_add(calculator::mul_rate_u(
+                    long_position.mm_usd.min(short_position.mm_usd),
+                    hedge_mm_offset_rate.cast()?,
+                )?)?;
+            }
+        }
+        Ok(hedged_position_mm)
+    }
+
     pub fn get_total_used_value(
         &self,
         trade_token_map: &TradeTokenMap,
@@ -799,17 +766,6 @@ impl User {
         Ok((total_used_value, total_token_borrowing_value))
     }
 
-    pub fn cancel_all_cross_orders(&mut self) -> BumpResult<()> {
-        let user_orders_length = self.orders.len();
-        for index in 0..user_orders_length {
-            let order = self.orders[index];
-            if order.status.eq(&OrderStatus::USING) && order.is_portfolio_margin {
-                self.cancel_user_order(index)?;
-            }
-        }
-        Ok(())
-    }
-
     pub fn cancel_order<'info>(
         &mut self,
         order: &UserOrder,
@@ -819,7 +775,8 @@ impl User {
         bump_signer: &AccountInfo<'info>,
         state: &Account<'info, State>,
     ) -> BumpResult<()> {
-        self.delete_order(order.order_id)?;
+        emit!(AddOrDeleteUserOrderEvent { user_key: self.key, order: *order, is_add: false });
+        self.remove_open_order()?;
         if order.position_side.eq(&PositionSide::INCREASE) && order.is_portfolio_margin {
             self.sub_order_hold_in_usd(order.order_margin)?;
         } else if order.position_side.eq(&PositionSide::INCREASE)
@@ -839,3 +796,40 @@ impl User {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::math::constants::RATE_PRECISION;
+    use crate::state::infrastructure::user_position::{PositionStatus, UserPosition};
+    use crate::state::user::User;
+
+    fn cross_position(symbol: u8, is_long: bool, mm_usd: u128) -> UserPosition {
+        UserPosition {
+            symbol: [symbol; 32],
+            is_long,
+            is_portfolio_margin: true,
+            mm_usd,
+            status: PositionStatus::USING,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_get_hedged_position_mm() {
+        let mut user: User = bytemuck::Zeroable::zeroed();
+        user.hedge_mode = true;
+        user.positions[0] = cross_position(1, true, 100);
+        user.positions[1] = cross_position(1, false, 40);
+        //an unhedged leg and an isolate leg offset nothing
+        user.positions[2] = cross_position(2, true, 70);
+        user.positions[3] =
+            UserPosition { is_portfolio_margin: false, ..cross_position(2, false, 30) };
+
+        assert_eq!(user.get_hedged_position_mm(0).unwrap(), 0);
+        assert_eq!(user.get_hedged_position_mm(RATE_PRECISION as u32 / 2).unwrap(), 20);
+        assert_eq!(user.get_hedged_position_mm(RATE_PRECISION as u32).unwrap(), 40);
+
+        user.hedge_mode = false;
+        assert_eq!(user.get_hedged_position_mm(RATE_PRECISION as u32).unwrap(), 0);
+    }
+}
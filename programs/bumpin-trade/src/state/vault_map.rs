@@ -29,6 +29,14 @@ impl<'a> VaultMap<'a> {
         Ok(account)
     }
 
+    pub fn get_account_by_owner_and_mint(
+        &self,
+        owner: &Pubkey,
+        mint: &Pubkey,
+    ) -> Option<&Account<'a, TokenAccount>> {
+        self.0.values().find(|account| account.owner.eq(owner) && account.mint.eq(mint))
+    }
+
     pub fn load(remaining_accounts: &'a [AccountInfo<'a>]) -> BumpResult<VaultMap<'a>> {
         let mut token_account_map: VaultMap = VaultMap(BTreeMap::new());
         for account_info in remaining_accounts.iter() {
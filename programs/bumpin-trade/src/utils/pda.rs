@@ -17,6 +17,23 @@ pub fn generate_position_key(
     Ok(address)
 }
 
+/// Hedge mode keys a position by side as well, so a long and a short of the same symbol and
+/// margin mode are held side by side.
+pub fn generate_hedge_position_key(
+    user: &Pubkey,
+    symbol: [u8; 32],
+    is_portfolio_margin: bool,
+    is_long: bool,
+    program_id: &Pubkey,
+) -> BumpResult<Pubkey> {
+    let is_portfolio_margin_bytes: &[u8] = if is_portfolio_margin { &[1] } else { &[0] };
+    let is_long_bytes: &[u8] = if is_long { &[1] } else { &[0] };
+    let seeds: &[&[u8]] = &[user.as_ref(), &symbol, is_portfolio_margin_bytes, is_long_bytes];
+
+    let (address, _bump_seed) = Pubkey::find_program_address(seeds, program_id);
+    Ok(address)
+}
+
 pub fn generate_pool_vault_key(pool_index: u16, program_id: &Pubkey) -> BumpResult<Pubkey> {
     let binding = pool_index.to_le_bytes();
     let seeds: &[&[u8]] = &["pool_mint_vault".as_ref(), binding.as_ref()];
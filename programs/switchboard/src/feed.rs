@@ -0,0 +1,25 @@
+use arrayref::array_mut_ref;
+
+// byte layout of the switchboard on-demand PullFeedAccountData account, values carry 18 decimals
+pub const FEED_ACCOUNT_SIZE: usize = 8 + 3200;
+pub const DISCRIMINATOR: [u8; 8] = [196, 27, 108, 196, 10, 215, 219, 40];
+pub const FEED_HASH_OFFSET: usize = 8 + 2112;
+pub const LAST_UPDATE_TIMESTAMP_OFFSET: usize = 8 + 2208;
+pub const RESULT_VALUE_OFFSET: usize = 8 + 2256;
+pub const RESULT_STD_DEV_OFFSET: usize = RESULT_VALUE_OFFSET + 16;
+pub const RESULT_SLOT_OFFSET: usize = RESULT_VALUE_OFFSET + 104;
+
+pub fn write_discriminator(data: &mut [u8]) {
+    *array_mut_ref![data, 0, 8] = DISCRIMINATOR;
+}
+
+pub fn write_feed_hash(data: &mut [u8], feed_hash: &[u8; 32]) {
+    *array_mut_ref![data, FEED_HASH_OFFSET, 32] = *feed_hash;
+}
+
+pub fn write_result(data: &mut [u8], value: i128, std_dev: i128, slot: u64, timestamp: i64) {
+    *array_mut_ref![data, RESULT_VALUE_OFFSET, 16] = value.to_le_bytes();
+    *array_mut_ref![data, RESULT_STD_DEV_OFFSET, 16] = std_dev.to_le_bytes();
+    *array_mut_ref![data, RESULT_SLOT_OFFSET, 8] = slot.to_le_bytes();
+    *array_mut_ref![data, LAST_UPDATE_TIMESTAMP_OFFSET, 8] = timestamp.to_le_bytes();
+}
@@ -0,0 +1,44 @@
+use anchor_lang::prelude::*;
+pub mod feed;
+
+declare_id!("27ZntGb9wxMjmwN7vQ91Y4Gf7UYYB3GUG7XF3eAvJtSW");
+
+#[program]
+pub mod switchboard {
+    use super::*;
+
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        value: i128,
+        std_dev: i128,
+        feed_hash: [u8; 32],
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let mut data = ctx.accounts.feed.try_borrow_mut_data()?;
+        feed::write_discriminator(&mut data);
+        feed::write_feed_hash(&mut data, &feed_hash);
+        feed::write_result(&mut data, value, std_dev, clock.slot, clock.unix_timestamp);
+        Ok(())
+    }
+
+    pub fn set_value(ctx: Context<SetValue>, value: i128, std_dev: i128) -> Result<()> {
+        let clock = Clock::get()?;
+        let mut data = ctx.accounts.feed.try_borrow_mut_data()?;
+        feed::write_result(&mut data, value, std_dev, clock.slot, clock.unix_timestamp);
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct SetValue<'info> {
+    /// CHECK: this program is just for testing
+    #[account(mut)]
+    pub feed: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    /// CHECK: this program is just for testing
+    #[account(mut)]
+    pub feed: AccountInfo<'info>,
+}